@@ -6,7 +6,7 @@ use std::{
 extern crate libc;
 
 extern crate rsmnl as mnl;
-use mnl::{Attr, CbResult, CbStatus, MsgVec, Msghdr, NestAttr, Socket};
+use mnl::{Attr, CbResult, CbStatus, MsgVec, Msghdr, Socket};
 
 mod linux_bindings;
 use linux_bindings as linux;
@@ -32,8 +32,8 @@ fn data_attr_cb<'a, 'b>(
 }
 
 fn parse_genl_mc_grps(nested: &Attr) -> CbResult {
-    let mut nest = NestAttr::new(nested);
-    while let Some(attr) = nest.next() {
+    for attr in nested.nested() {
+        let attr = attr?;
         // let tb: [Option<&Attr>; libc::CTRL_ATTR__MAX as usize + 1] = Default::default();
         let mut tb: [Option<&Attr>; linux::__CTRL_ATTR_MAX as usize] = Default::default();
         attr.parse_nested(data_attr_cb(&mut tb))?;
@@ -49,8 +49,8 @@ fn parse_genl_mc_grps(nested: &Attr) -> CbResult {
 }
 
 fn parse_genl_family_ops(nested: &Attr) -> CbResult {
-    let mut nest = NestAttr::new(nested);
-    while let Some(attr) = nest.next() {
+    for attr in nested.nested() {
+        let attr = attr?;
         // let tb: [Option<&Attr>; libc::CTRL_ATTR_OP_MAX as usize + 1] = Default::default();
         let mut tb: [Option<&Attr>; libc::CTRL_ATTR_OP_FLAGS as usize + 1] = Default::default();
         attr.parse_nested(data_attr_cb(&mut tb))?;
@@ -1,8 +1,6 @@
 use std::{env, mem, process, vec::Vec};
 
-extern crate errno;
 extern crate libc;
-use errno::Errno;
 
 extern crate rsmnl as mnl;
 use mnl::{Attr, CbResult, CbStatus, MsgVec, Msghdr, Socket};
@@ -53,7 +51,7 @@ fn log_cb(nlh: &Msghdr) -> CbResult {
     Ok(CbStatus::Ok)
 }
 
-fn nflog_build_cfg_pf_request(nlv: &mut MsgVec, command: u8) -> Result<(), Errno> {
+fn nflog_build_cfg_pf_request(nlv: &mut MsgVec, command: u8) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((libc::NFNL_SUBSYS_ULOG << 8) | linux::nfulnl_msg_types_NFULNL_MSG_CONFIG as i32) as u16;
@@ -69,7 +67,7 @@ fn nflog_build_cfg_pf_request(nlv: &mut MsgVec, command: u8) -> Result<(), Errno
     Ok(())
 }
 
-fn nflog_build_cfg_request(nlv: &mut MsgVec, command: u8, qnum: u16) -> Result<(), Errno> {
+fn nflog_build_cfg_request(nlv: &mut MsgVec, command: u8, qnum: u16) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((libc::NFNL_SUBSYS_ULOG << 8) | linux::nfulnl_msg_types_NFULNL_MSG_CONFIG as i32) as u16;
@@ -86,7 +84,7 @@ fn nflog_build_cfg_request(nlv: &mut MsgVec, command: u8, qnum: u16) -> Result<(
     Ok(())
 }
 
-fn nflog_build_cfg_params(nlv: &mut MsgVec, mode: u8, range: u32, qnum: u16) -> Result<(), Errno> {
+fn nflog_build_cfg_params(nlv: &mut MsgVec, mode: u8, range: u32, qnum: u16) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((libc::NFNL_SUBSYS_ULOG << 8) | linux::nfulnl_msg_types_NFULNL_MSG_CONFIG as i32) as u16;
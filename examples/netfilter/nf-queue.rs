@@ -2,9 +2,6 @@ use std::{env, mem, process, vec::Vec};
 
 extern crate libc;
 
-extern crate errno;
-use errno::Errno;
-
 extern crate rsmnl as mnl;
 use mnl::{Attr, CbResult, CbStatus, MsgVec, Msghdr, Socket};
 
@@ -46,7 +43,7 @@ fn queue_cb(packet_id: &mut u32) -> impl FnMut(&Msghdr) -> CbResult + '_ {
     }
 }
 
-fn nfq_build_cfg_pf_request(nlv: &mut MsgVec, command: u8) -> Result<(), Errno> {
+fn nfq_build_cfg_pf_request(nlv: &mut MsgVec, command: u8) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((linux::NFNL_SUBSYS_QUEUE << 8) | linux::nfqnl_msg_types_NFQNL_MSG_CONFIG) as u16;
@@ -66,7 +63,7 @@ fn nfq_build_cfg_pf_request(nlv: &mut MsgVec, command: u8) -> Result<(), Errno>
     Ok(())
 }
 
-fn nfq_build_cfg_request(nlv: &mut MsgVec, command: u8, queue_num: u16) -> Result<(), Errno> {
+fn nfq_build_cfg_request(nlv: &mut MsgVec, command: u8, queue_num: u16) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((libc::NFNL_SUBSYS_QUEUE << 8) | linux::nfqnl_msg_types_NFQNL_MSG_CONFIG as i32) as u16;
@@ -87,12 +84,7 @@ fn nfq_build_cfg_request(nlv: &mut MsgVec, command: u8, queue_num: u16) -> Resul
     Ok(())
 }
 
-fn nfq_build_cfg_params(
-    nlv: &mut MsgVec,
-    mode: u8,
-    range: u32,
-    queue_num: u16,
-) -> Result<(), Errno> {
+fn nfq_build_cfg_params(nlv: &mut MsgVec, mode: u8, range: u32, queue_num: u16) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((libc::NFNL_SUBSYS_QUEUE << 8) | linux::nfqnl_msg_types_NFQNL_MSG_CONFIG as i32) as u16;
@@ -112,7 +104,7 @@ fn nfq_build_cfg_params(
     Ok(())
 }
 
-fn nfq_build_verdict(nlv: &mut MsgVec, id: u32, queue_num: u16, verd: u32) -> Result<(), Errno> {
+fn nfq_build_verdict(nlv: &mut MsgVec, id: u32, queue_num: u16, verd: u32) -> mnl::Result<()> {
     let mut nlh = nlv.put_header();
     nlh.nlmsg_type =
         ((libc::NFNL_SUBSYS_QUEUE << 8) | linux::nfqnl_msg_types_NFQNL_MSG_VERDICT as i32) as u16;
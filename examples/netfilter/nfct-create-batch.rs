@@ -1,25 +1,14 @@
-use std::{
-    io,
-    net::Ipv4Addr,
-    os::unix::io::AsRawFd,
-    os::unix::io::{FromRawFd, IntoRawFd},
-    time::{Duration, SystemTime, UNIX_EPOCH},
-};
-
-extern crate errno;
-extern crate libc;
-use errno::Errno;
+use std::net::Ipv4Addr;
 
-extern crate mio;
-use mio::{net::UdpSocket, Events, Interest, Poll, Token};
+extern crate libc;
 
 extern crate rsmnl as mnl;
-use mnl::{CbResult, CbStatus, MsgVec, Msghdr, Socket};
+use mnl::{BatchWriter, ChunkAcks, MsgVec, Socket};
 
 mod linux_bindings;
 use linux_bindings as linux;
 
-fn put_msg(nlv: &mut MsgVec, i: u16, seq: u32) -> Result<(), Errno> {
+fn put_msg(nlv: &mut MsgVec, i: u16, seq: u32) -> mnl::Result<()> {
     let nlh = nlv.put_header();
     nlh.nlmsg_type =
         (libc::NFNL_SUBSYS_CTNETLINK << 8) as u16 | linux::cntl_msg_types_IPCTNL_MSG_CT_NEW as u16;
@@ -106,89 +95,38 @@ fn put_msg(nlv: &mut MsgVec, i: u16, seq: u32) -> Result<(), Errno> {
     Ok(())
 }
 
-fn error_cb(nlh: &Msghdr) -> CbResult {
-    let err = nlh.payload::<libc::nlmsgerr>()?;
-    if err.error != 0 {
-        println!(
-            "message with seq {} has failed: {}",
-            nlh.nlmsg_seq,
-            io::Error::from_raw_os_error(-err.error)
-        );
-    }
-    Ok(CbStatus::Ok)
-}
-
-fn send_batch(nl: &mut Socket, nlv: &MsgVec, portid: u32) -> Result<(), String> {
-    nl.sendto(nlv)
-        .map_err(|errno| format!("mnl_socket_sendto: {}", errno))?;
-
-    let mut poll = Poll::new().unwrap();
-    let token = Token(nl.as_raw_fd() as usize);
-    let mut listener = unsafe { UdpSocket::from_raw_fd(nl.as_raw_fd()) };
-    poll.registry()
-        .register(&mut listener, token, Interest::READABLE)
-        .unwrap();
-    let mut events = Events::with_capacity(256);
-
-    let mut buf = mnl::default_buffer();
-    let mut ctlcbs: [Option<fn(&Msghdr) -> CbResult>; libc::NLMSG_ERROR as usize + 1] =
-        Default::default();
-    ctlcbs[libc::NLMSG_ERROR as usize] = Some(error_cb);
-
-    loop {
-        poll.poll(&mut events, Some(Duration::new(0, 0))).unwrap();
-        if events.is_empty() {
-            // timed out
-            listener.into_raw_fd();
-            return Ok(());
-        }
-
-        loop {
-            let nrecv = match nl.recvfrom(&mut buf) {
-                Err(errno) => {
-                    if errno.0 == libc::EAGAIN {
-                        break;
-                    } else {
-                        return Err(format!("mnl_socket_recvfrom: {}", errno));
-                    }
-                }
-                Ok(n) => n,
-            };
-            mnl::cb_run2(&buf[0..nrecv], 0, portid, mnl::NOCB, &mut ctlcbs)
-                .map_err(|errno| format!("mnl_cb_run2: {}", errno))?;
-        }
-    }
-}
-
 fn main() -> Result<(), String> {
     let mut nl = Socket::open(libc::NETLINK_NETFILTER, 0)
         .map_err(|errno| format!("mnl_socket_open: {}", errno))?;
-    // mio restriction, can handle only edge-trigger
-    nl.set_nonblock().unwrap();
     nl.bind(0, mnl::SOCKET_AUTOPID)
         .map_err(|errno| format!("mnl_socket_bind: {}", errno))?;
-    let portid = nl.portid();
 
+    // ENOSPC returns at recvfrom if a datagram is too big
+    let mut writer = BatchWriter::new(40000);
     let mut nlv = MsgVec::new();
-    let seq = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as u32;
     for i in 1024u16..65535 {
-        put_msg(&mut nlv, i, seq + i as u32 - 1024).unwrap();
-        // MsgVec has no size limit,
-        // but ENOSPC returns at recvfrom if it's too big
-        if nlv.len() < 40000 {
-            continue;
-        }
-        send_batch(&mut nl, &mut nlv, portid)?;
+        let seq = nl.next_seq();
         nlv.reset();
+        put_msg(&mut nlv, i, seq).unwrap();
+        writer.push(&nlv).unwrap();
+        // send the chunks filled, the last one is being filled
+        while writer.chunks() > 1 {
+            report(writer.send_next(&nl))?;
+        }
     }
 
-    // check if there is any message in the batch not sent yet.
-    if nlv.len() != 0 {
-        send_batch(&mut nl, &mut nlv, portid)?;
+    // the rest not sent yet.
+    while !writer.is_empty() {
+        report(writer.send_next(&nl))?;
     }
 
     Ok(())
 }
+
+fn report(acks: mnl::Result<Option<ChunkAcks>>) -> Result<(), String> {
+    let acks = acks.map_err(|err| format!("mnl_socket_recvfrom: {}", err))?;
+    for (seq, err) in acks.iter().flat_map(|acks| acks.errors()) {
+        println!("message with seq {} has failed: {}", seq, err);
+    }
+    Ok(())
+}
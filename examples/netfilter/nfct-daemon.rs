@@ -2,22 +2,18 @@ use std::{
     collections::HashMap,
     env, mem,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
-    os::unix::io::{AsRawFd, FromRawFd},
+    os::unix::io::AsRawFd,
     process,
     time::Duration,
 };
 
 extern crate libc;
-use libc::{c_int, c_void, socklen_t};
-
-extern crate errno;
-use errno::Errno;
 
 extern crate mio;
-use mio::{net::UdpSocket, Events, Interest, Poll, Token};
+use mio::{Events, Interest, Poll, Token};
 
 extern crate rsmnl as mnl;
-use mnl::{Attr, CbResult, CbStatus, GenError, MsgVec, Msghdr, Socket};
+use mnl::{Attr, CbResult, CbStatus, MsgVec, Msghdr, Socket, SocketBuilder};
 
 mod linux_bindings;
 use linux_bindings as linux;
@@ -43,7 +39,7 @@ fn data_attr_cb<'a, 'b>(
     }
 }
 
-fn parse_counters(nest: &Attr, ns: &mut Nstats) -> Result<(), Errno> {
+fn parse_counters(nest: &Attr, ns: &mut Nstats) -> mnl::Result<()> {
     let mut tb: [Option<&Attr>; linux::ctattr_counters___CTA_COUNTERS_MAX as usize] =
         [None; linux::ctattr_counters___CTA_COUNTERS_MAX as usize];
 
@@ -56,7 +52,7 @@ fn parse_counters(nest: &Attr, ns: &mut Nstats) -> Result<(), Errno> {
     Ok(())
 }
 
-fn parse_ip(nest: &Attr) -> Result<Option<IpAddr>, Errno> {
+fn parse_ip(nest: &Attr) -> mnl::Result<Option<IpAddr>> {
     let mut tb: [Option<&Attr>; linux::ctattr_ip___CTA_IP_MAX as usize] =
         [None; linux::ctattr_ip___CTA_IP_MAX as usize];
 
@@ -71,7 +67,7 @@ fn parse_ip(nest: &Attr) -> Result<Option<IpAddr>, Errno> {
     Ok(None)
 }
 
-fn parse_tuple(nest: &Attr) -> Result<Option<IpAddr>, Errno> {
+fn parse_tuple(nest: &Attr) -> mnl::Result<Option<IpAddr>> {
     let mut tb: [Option<&Attr>; linux::ctattr_tuple___CTA_TUPLE_MAX as usize] =
         [None; linux::ctattr_tuple___CTA_TUPLE_MAX as usize];
 
@@ -108,29 +104,27 @@ fn data_cb(hmap: &mut HashMap<IpAddr, Box<Nstats>>) -> impl FnMut(&Msghdr) -> Cb
     }
 }
 
-fn handle(nl: &mut Socket, hmap: &mut HashMap<IpAddr, Box<Nstats>>) -> CbResult {
+fn handle(nl: &mut Socket, hmap: &mut HashMap<IpAddr, Box<Nstats>>) -> mnl::Result<usize> {
     let mut buf = mnl::dump_buffer();
-    match nl.recvfrom(&mut buf) {
-        Ok(nrecv) => return mnl::cb_run(&buf[0..nrecv], 0, 0, Some(data_cb(hmap))),
-
-        Err(errno) => {
-            if errno.0 == libc::ENOBUFS {
-                println!(
-                    "The daemon has hit ENOBUFS, you can \
-			  increase the size of your receiver \
-			  buffer to mitigate this or enable \
-			  reliable delivery."
-                );
-            } else {
-                println!("mnl_socket_recvfrom: {}", errno);
-            }
-            return mnl::gen_errno!(errno.0);
+    // NLMSG_DONE of the dump stops cb_run, but not draining.
+    nl.drain(&mut buf, |batch| {
+        mnl::cb_run(batch, 0, 0, Some(data_cb(hmap))).map(|_| CbStatus::Ok)
+    })
+    .map_err(|err| {
+        if err.errno() == Some(mnl::Errno(libc::ENOBUFS)) {
+            println!(
+                "The daemon has hit ENOBUFS, you can \
+		  increase the size of your receiver \
+		  buffer to mitigate this or enable \
+		  reliable delivery."
+            );
+        } else {
+            println!("mnl_socket_recvfrom: {}", err);
         }
-    }
+        err
+    })
 }
 
-pub const SO_RECVBUFFORCE: c_int = 33;
-
 fn main() -> Result<(), String> {
     let args: Vec<_> = env::args().collect();
     if args.len() != 2 {
@@ -148,28 +142,15 @@ fn main() -> Result<(), String> {
     };
 
     // Open netlink socket to operate with netfilter
-    let mut nl = Socket::open(libc::NETLINK_NETFILTER, 0)
-        .map_err(|errno| format!("mnl_socket_open: {}", errno))?;
-
+    //
     // Subscribe to destroy events to avoid leaking counters. The same
     // socket is used to periodically atomically dump and reset counters.
-    nl.bind(linux::NF_NETLINK_CONNTRACK_DESTROY, mnl::SOCKET_AUTOPID)
-        .map_err(|errno| format!("mnl_socket_bind: {}", errno))?;
-
-    // Set netlink receiver buffer to 16 MBytes, to avoid packet drops
-    unsafe {
-        let buffersize: c_int = 1 << 22;
-        libc::setsockopt(
-            nl.as_raw_fd(),
-            libc::SOL_SOCKET,
-            SO_RECVBUFFORCE,
-            &buffersize as *const _ as *const c_void,
-            mem::size_of::<socklen_t>() as u32,
-        );
-    }
-
-    // The two tweaks below enable reliable event delivery, packets may
-    // be dropped if the netlink receiver buffer overruns. This happens ...
+    //
+    // Set netlink receiver buffer to 16 MBytes, to avoid packet drops.
+    //
+    // The two tweaks, broadcast_error and no_enobufs enable reliable event
+    // delivery, packets may be dropped if the netlink receiver buffer
+    // overruns. This happens ...
     //
     // a) if the kernel spams this user-space process until the receiver
     //    is filled.
@@ -178,8 +159,19 @@ fn main() -> Result<(), String> {
     //
     // b) if the user-space process does not pull messages from the
     //    receiver buffer so often.
-    let _ = nl.set_broadcast_error(true);
-    let _ = nl.set_no_enobufs(true);
+    //
+    // mio is edge-triggered, the socket is drained on each event.
+    let (mut nl, rejected) = SocketBuilder::new(libc::NETLINK_NETFILTER)
+        .nonblock()
+        .rcvbuf(1 << 22)
+        .broadcast_error(true)
+        .no_enobufs(true)
+        .bind(linux::NF_NETLINK_CONNTRACK_DESTROY, mnl::SOCKET_AUTOPID)
+        .build()
+        .map_err(|errno| format!("mnl_socket_open: {}", errno))?;
+    for (opt, err) in rejected {
+        eprintln!("{:?} is not set: {}", opt, err);
+    }
 
     let mut nlv = MsgVec::new();
     let mut nlh = nlv.put_header();
@@ -206,7 +198,6 @@ fn main() -> Result<(), String> {
 
     // mio initializations
     let token = Token(nl.as_raw_fd() as usize);
-    let mut listener = unsafe { UdpSocket::from_raw_fd(nl.as_raw_fd()) };
     let mut timer = timerfd::Timerfd::create(libc::CLOCK_MONOTONIC, 0).unwrap();
     timer
         .settime(
@@ -222,7 +213,7 @@ fn main() -> Result<(), String> {
     let mut poll = Poll::new().unwrap();
     // Start listening for incoming connections
     poll.registry()
-        .register(&mut listener, token, Interest::READABLE)
+        .register(&mut nl, token, Interest::READABLE)
         .unwrap();
     poll.registry()
         .register(&mut timer, Token(0), Interest::READABLE)
@@ -0,0 +1,107 @@
+use std::{
+    mem,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+extern crate libc;
+
+extern crate rsmnl as mnl;
+use mnl::{Attr, AttrTbl, CbResult, CbStatus, MsgVec, Msghdr, Socket};
+
+mod linux_bindings;
+use linux_bindings as linux;
+
+#[derive(AttrTbl)]
+struct LinkinfoAttrs<'a> {
+    #[nla(type = linux::IFLA_INFO_KIND, data = NulString)]
+    kind: Option<&'a Attr<'a>>,
+}
+
+#[derive(AttrTbl)]
+struct LinkAttrs<'a> {
+    #[nla(type = libc::IFLA_MTU, data = U32)]
+    mtu: Option<&'a Attr<'a>>,
+    #[nla(type = libc::IFLA_IFNAME, data = NulString)]
+    ifname: Option<&'a Attr<'a>>,
+    #[nla(type = libc::IFLA_ADDRESS, data = Binary)]
+    address: Option<&'a Attr<'a>>,
+    #[nla(type = linux::IFLA_LINKINFO, nested = LinkinfoAttrs)]
+    linkinfo: Option<&'a Attr<'a>>,
+}
+
+fn data_cb(nlh: &Msghdr) -> CbResult {
+    let ifm = nlh.payload::<linux::ifinfomsg>().unwrap();
+    print!(
+        "index={} type={} flags=0x{:x} family={} ",
+        ifm.ifi_index, ifm.ifi_type, ifm.ifi_flags, ifm.ifi_family
+    );
+
+    if ifm.ifi_flags & libc::IFF_RUNNING as u32 != 0 {
+        print!("[RUNNING] ");
+    } else {
+        print!("[NOT RUNNING] ");
+    }
+
+    // skip unsupported attribute in user-space
+    let tb = LinkAttrs::from_nlmsg(mem::size_of::<linux::ifinfomsg>(), nlh)?;
+    if let Some(mtu) = tb.mtu()? {
+        print!("mtu={} ", mtu);
+    }
+    if let Some(name) = tb.ifname()? {
+        print!("name={} ", name);
+    }
+    if let Some(kind) = tb.linkinfo()?.map(|info| info.kind()).transpose()? {
+        print!("kind={} ", kind.unwrap_or("unknown"));
+    }
+    if let Some(hwaddr) = tb.address()? {
+        print!(
+            "hwaddr={}",
+            hwaddr
+                .iter()
+                .map(|&e| format!("{:02x}", e))
+                .collect::<Vec<_>>()
+                .join(":")
+        );
+    }
+    println!();
+    Ok(CbStatus::Ok)
+}
+
+fn main() -> Result<(), String> {
+    let mut nlv = MsgVec::new();
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::RTM_GETLINK;
+    nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+    let seq = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32;
+    nlh.nlmsg_seq = seq;
+    let rt: &mut linux::rtgenmsg = nlv.put_extra_header().unwrap();
+    rt.rtgen_family = libc::AF_PACKET as u8;
+
+    let mut nl = Socket::open(libc::NETLINK_ROUTE, 0)
+        .map_err(|errno| format!("mnl_socket_open: {}", errno))?;
+
+    nl.bind(0, mnl::SOCKET_AUTOPID)
+        .map_err(|errno| format!("mnl_socket_bind: {}", errno))?;
+    let portid = nl.portid();
+
+    nl.sendto(&nlv)
+        .map_err(|errno| format!("mnl_socket_sendto: {}", errno))?;
+
+    let mut buf = mnl::dump_buffer();
+    loop {
+        let nrecv = nl
+            .recvfrom(&mut buf)
+            .map_err(|errno| format!("mnl_socket_recvfrom: {}", errno))?;
+
+        match mnl::cb_run(&buf[..nrecv], seq, portid, Some(data_cb)) {
+            Ok(CbStatus::Ok) => continue,
+            Ok(CbStatus::Stop) => break,
+            Err(errno) => return Err(format!("mnl_cb_run: {}", errno)),
+        };
+    }
+
+    Ok(())
+}
@@ -26,7 +26,7 @@ fn data_attr_cb2<'a, 'b>(
     }
 }
 
-fn attributes_show_ipv4(tb: &[Option<&Attr>]) -> Result<(), Box<dyn std::error::Error>> {
+fn attributes_show_ipv4(tb: &[Option<&Attr>]) -> mnl::Result<()> {
     if let Some(attr) = tb[libc::RTA_TABLE as usize] {
         print!("table={} ", attr.value_ref::<u32>()?);
     }
@@ -65,7 +65,7 @@ fn attributes_show_ipv4(tb: &[Option<&Attr>]) -> Result<(), Box<dyn std::error::
     Ok(())
 }
 
-fn attributes_show_ipv6(tb: &[Option<&Attr>]) -> Result<(), Box<dyn std::error::Error>> {
+fn attributes_show_ipv6(tb: &[Option<&Attr>]) -> mnl::Result<()> {
     if let Some(attr) = tb[libc::RTA_TABLE as usize] {
         print!("table={} ", attr.value_ref::<u32>()?);
     }
@@ -0,0 +1,313 @@
+//! # rsmnl-derive
+//!
+//! `#[derive(AttrTbl)]` for [rsmnl](https://github.com/chamaken/rsmnl-core),
+//! generates `rsmnl::AttrTbl` implementation and typed getters for a struct
+//! whose fields are all `Option<&'a Attr<'a>>`. Each field has to be annotated
+//! with its attribute type and either its data type or nested table:
+//!
+//! ```ignore
+//! #[derive(AttrTbl)]
+//! struct LinkAttrs<'a> {
+//!     #[nla(type = libc::IFLA_MTU, data = U32)]
+//!     mtu: Option<&'a Attr<'a>>,
+//!     #[nla(type = libc::IFLA_IFNAME, data = NulString)]
+//!     ifname: Option<&'a Attr<'a>>,
+//!     #[nla(type = libc::IFLA_ADDRESS, data = Binary)]
+//!     address: Option<&'a Attr<'a>>,
+//!     #[nla(type = libc::IFLA_LINKINFO, nested = LinkinfoAttrs)]
+//!     linkinfo: Option<&'a Attr<'a>>,
+//! }
+//! ```
+//!
+//! `data` is the name of `rsmnl::AttrDataType` variant, which decides the
+//! return type of the getter:
+//!
+//! | data                  | getter returns                  |
+//! |-----------------------|---------------------------------|
+//! | `U8` .. `U64`, `MSecs`| `Result<Option<u8>>` .. `u64`   |
+//! | `String`, `NulString` | `Result<Option<&'a str>>`       |
+//! | `Binary`              | `Result<Option<&'a [u8]>>`      |
+//! | `Binary, value = T`   | `Result<Option<T>>`             |
+//! | `Flag`                | `Result<bool>`                  |
+//! | `Nested`              | `Result<Option<Attrs<'a>>>`     |
+//! | `UNSPEC`              | no getter                       |
+//!
+//! and `nested = T` makes the getter to return `Result<Option<T<'a>>>`, where
+//! `T` is an another `AttrTbl`. The value is validated by its data type on
+//! getting, and `AttrTbl::validate()` validates all attributes in the table.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Expr, Fields, GenericParam,
+    Ident, Lifetime, Result, Type,
+};
+
+enum Kind {
+    Data(Ident, Option<Type>),
+    Nested(Type),
+}
+
+struct Nla {
+    ident: Ident,
+    atype: Expr,
+    kind: Kind,
+}
+
+const DATA_TYPES: &[&str] = &[
+    "UNSPEC",
+    "U8",
+    "U16",
+    "U32",
+    "U64",
+    "String",
+    "Flag",
+    "MSecs",
+    "Nested",
+    "NulString",
+    "Binary",
+];
+
+fn parse_field(field: &syn::Field) -> Result<Nla> {
+    let ident = field.ident.clone().unwrap();
+    let mut atype = None;
+    let mut data = None;
+    let mut value = None;
+    let mut nested = None;
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("nla")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("type") {
+                atype = Some(meta.value()?.parse::<Expr>()?);
+            } else if meta.path.is_ident("data") {
+                let dtype = meta.value()?.parse::<Ident>()?;
+                if !DATA_TYPES.iter().any(|t| dtype == t) {
+                    return Err(meta.error(format!("unknown data type: {}", dtype)));
+                }
+                data = Some(dtype);
+            } else if meta.path.is_ident("value") {
+                value = Some(meta.value()?.parse::<Type>()?);
+            } else if meta.path.is_ident("nested") {
+                nested = Some(meta.value()?.parse::<Type>()?);
+            } else {
+                return Err(meta.error("expected `type`, `data`, `value` or `nested`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let atype = atype.ok_or_else(|| Error::new(field.span(), "missing #[nla(type = ..)]"))?;
+    let kind = match (data, nested) {
+        (Some(_), Some(_)) => {
+            return Err(Error::new(
+                field.span(),
+                "`data` and `nested` are exclusive",
+            ))
+        }
+        (None, None) => {
+            return Err(Error::new(
+                field.span(),
+                "missing #[nla(data = ..)] or #[nla(nested = ..)]",
+            ))
+        }
+        (None, Some(t)) => {
+            if value.is_some() {
+                return Err(Error::new(field.span(), "`value` requires `data = Binary`"));
+            }
+            Kind::Nested(t)
+        }
+        (Some(d), None) => {
+            if value.is_some() && d != "Binary" {
+                return Err(Error::new(field.span(), "`value` requires `data = Binary`"));
+            }
+            Kind::Data(d, value)
+        }
+    };
+
+    Ok(Nla { ident, atype, kind })
+}
+
+fn getter(nla: &Nla, vis: &syn::Visibility, lt: &Lifetime) -> TokenStream2 {
+    let ident = &nla.ident;
+    match &nla.kind {
+        Kind::Nested(t) => quote! {
+            #vis fn #ident(&self) -> rsmnl::Result<Option<#t<#lt>>> {
+                match self.#ident {
+                    Some(attr) => {
+                        attr.validate(rsmnl::AttrDataType::Nested)?;
+                        let mut tb = <#t<#lt> as rsmnl::AttrTbl<#lt>>::new();
+                        let mut count = 0;
+                        for nested in attr.nested() {
+                            let _ = rsmnl::AttrTbl::add(&mut tb, nested?, &mut count);
+                        }
+                        Ok(Some(tb))
+                    }
+                    None => Ok(None),
+                }
+            }
+        },
+        Kind::Data(d, value) => {
+            let (ret, get) = match d.to_string().as_str() {
+                "UNSPEC" => return quote! {},
+                "Flag" => {
+                    return quote! {
+                        #vis fn #ident(&self) -> rsmnl::Result<bool> {
+                            match self.#ident {
+                                Some(attr) => {
+                                    attr.validate(rsmnl::AttrDataType::Flag)?;
+                                    Ok(true)
+                                }
+                                None => Ok(false),
+                            }
+                        }
+                    }
+                }
+                "U8" => (quote! { u8 }, quote! { attr.value::<u8>()? }),
+                "U16" => (quote! { u16 }, quote! { attr.value::<u16>()? }),
+                "U32" => (quote! { u32 }, quote! { attr.value::<u32>()? }),
+                "U64" | "MSecs" => (quote! { u64 }, quote! { attr.value::<u64>()? }),
+                "String" => (quote! { &#lt str }, quote! { attr.str()? }),
+                "NulString" => (quote! { &#lt str }, quote! { attr.cstr()? }),
+                "Nested" => (quote! { rsmnl::Attrs<#lt> }, quote! { attr.nested() }),
+                _ => match value {
+                    Some(t) => (quote! { #t }, quote! { attr.value::<#t>()? }),
+                    None => (
+                        quote! { &#lt [u8] },
+                        quote! { &attr.as_bytes()[rsmnl::Attr::HDRLEN..] },
+                    ),
+                },
+            };
+            let validate = match value {
+                Some(t) => quote! { attr.validate2::<#t>(rsmnl::AttrDataType::#d)?; },
+                None => quote! { attr.validate(rsmnl::AttrDataType::#d)?; },
+            };
+            quote! {
+                #vis fn #ident(&self) -> rsmnl::Result<Option<#ret>> {
+                    match self.#ident {
+                        Some(attr) => {
+                            #validate
+                            Ok(Some(#get))
+                        }
+                        None => Ok(None),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn validator(nla: &Nla) -> TokenStream2 {
+    let ident = &nla.ident;
+    match &nla.kind {
+        Kind::Nested(_) => quote! {
+            if let Some(tb) = self.#ident()? {
+                rsmnl::AttrTbl::validate(&tb)?;
+            }
+        },
+        Kind::Data(d, _) if d == "UNSPEC" => quote! {},
+        Kind::Data(..) => quote! {
+            self.#ident()?;
+        },
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => return Err(Error::new(input.span(), "AttrTbl requires named fields")),
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "AttrTbl can be derived only for struct",
+            ))
+        }
+    };
+    let lts: Vec<&Lifetime> = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|p| match p {
+            GenericParam::Lifetime(l) => Some(&l.lifetime),
+            _ => None,
+        })
+        .collect();
+    if lts.len() != 1 || input.generics.params.len() != 1 {
+        return Err(Error::new(
+            input.generics.span(),
+            "AttrTbl requires exactly one lifetime parameter",
+        ));
+    }
+    let lt = lts[0];
+    let nlas = fields.iter().map(parse_field).collect::<Result<Vec<_>>>()?;
+
+    let index = format_ident!("__{}Index", name);
+    let idents: Vec<_> = nlas.iter().map(|n| &n.ident).collect();
+    let atypes: Vec<_> = nlas.iter().map(|n| &n.atype).collect();
+    let getters = nlas.iter().map(|n| getter(n, vis, lt));
+    let validators = nlas.iter().map(validator);
+
+    Ok(quote! {
+        #[doc(hidden)]
+        #[derive(Clone, Copy)]
+        #vis struct #index(u16);
+
+        #[automatically_derived]
+        impl ::std::convert::TryFrom<u16> for #index {
+            type Error = rsmnl::__private::Errno;
+
+            fn try_from(atype: u16) -> ::std::result::Result<Self, Self::Error> {
+                #(
+                    if atype == (#atypes) as u16 {
+                        return Ok(#index(atype));
+                    }
+                )*
+                Err(rsmnl::__private::Errno(rsmnl::__private::EOPNOTSUPP))
+            }
+        }
+
+        #[automatically_derived]
+        impl<#lt> rsmnl::AttrTbl<#lt> for #name<#lt> {
+            type Index = #index;
+
+            fn new() -> Self {
+                #name {
+                    #( #idents: None, )*
+                }
+            }
+
+            fn _set(&mut self, index: Self::Index, attr: &#lt rsmnl::Attr) {
+                #(
+                    if index.0 == (#atypes) as u16 {
+                        self.#idents = Some(attr);
+                        return;
+                    }
+                )*
+            }
+
+            fn validate(&self) -> rsmnl::Result<()> {
+                #( #validators )*
+                Ok(())
+            }
+        }
+
+        #[automatically_derived]
+        impl<#lt> #name<#lt> {
+            #( #getters )*
+        }
+    })
+}
+
+#[proc_macro_derive(AttrTbl, attributes(nla))]
+pub fn derive_attr_tbl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
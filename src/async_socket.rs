@@ -0,0 +1,170 @@
+use std::{
+    future::{self, Future},
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_core::Stream;
+use libc;
+use tokio::io::unix::AsyncFd;
+
+use crate::socket::{Replies, Reply, ReplyHandler};
+use crate::{CbResult, Error, MsgVec, Msghdr, OwnedMsg, Result, Socket};
+
+// EAGAIN is told to tokio as WouldBlock, which clears the readiness.
+fn would_block<T>(res: Result<T>) -> io::Result<Result<T>> {
+    match res {
+        Err(Error::Os(errno)) if errno.0 == libc::EAGAIN => Err(io::ErrorKind::WouldBlock.into()),
+        res => Ok(res),
+    }
+}
+
+/// A `Socket` registered to the tokio runtime.
+///
+/// Methods returning `Future` are `async fn` in effect, e.g.
+/// `nl.recv(&mut buf).await`.
+pub struct AsyncSocket {
+    inner: AsyncFd<Socket>,
+}
+
+impl AsyncSocket {
+    /// makes `nl` non-blocking and registers it to the current runtime.
+    ///
+    /// This must be called in the context of a tokio runtime with IO enabled.
+    pub fn new(mut nl: Socket) -> Result<Self> {
+        nl.set_nonblock()?;
+        // Socket owns the fd, which is closed only on drop.
+        let inner = unsafe { AsyncFd::register(nl) }.map_err(io::Error::from)?;
+        Ok(Self { inner })
+    }
+
+    pub fn get_ref(&self) -> &Socket {
+        self.inner.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut Socket {
+        self.inner.get_mut()
+    }
+
+    /// deregisters from the runtime and returns the inner `Socket`, which is
+    /// still non-blocking.
+    pub fn into_inner(self) -> Socket {
+        self.inner.into_inner()
+    }
+
+    pub fn poll_send<T: AsRef<[u8]>>(&self, cx: &mut Context<'_>, data: &T) -> Poll<Result<usize>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_write_ready(cx))?;
+            match guard.try_io(|inner| would_block(inner.get_ref().sendto(data))) {
+                Ok(Ok(res)) => return Poll::Ready(res),
+                Ok(Err(err)) => return Poll::Ready(Err(err.into())),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+            match guard.try_io(|inner| would_block(inner.get_ref().recvfrom(buf))) {
+                Ok(Ok(res)) => return Poll::Ready(res),
+                Ok(Err(err)) => return Poll::Ready(Err(err.into())),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// sends `data`, as `Socket::sendto()`.
+    pub fn send<'a, T: AsRef<[u8]>>(
+        &'a self,
+        data: &'a T,
+    ) -> impl Future<Output = Result<usize>> + 'a {
+        future::poll_fn(move |cx| self.poll_send(cx, data))
+    }
+
+    /// receives into `buf`, as `Socket::recvfrom()`.
+    pub fn recv<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = Result<usize>> + 'a {
+        future::poll_fn(move |cx| self.poll_recv(cx, buf))
+    }
+
+    /// send a request and wait for its ACK
+    ///
+    /// The async version of `Socket::request()`, the sequence number is
+    /// assigned on call, not on the first poll.
+    pub fn request<'a, F: FnMut(&Msghdr) -> CbResult + 'a>(
+        &'a mut self,
+        nlv: &'a mut MsgVec,
+        handler: F,
+    ) -> impl Future<Output = Result<()>> + 'a {
+        let (seq, mut err) = match self.get_mut().prepare(nlv, libc::NLM_F_ACK as u16) {
+            Ok(seq) => (seq, None),
+            Err(err) => (0, Some(err)),
+        };
+        let nl = &*self;
+        let nlv = &*nlv;
+        let mut replies = Replies::new(seq, true);
+        let mut sent = false;
+        let mut handler = ReplyHandler::new(handler);
+        future::poll_fn(move |cx| {
+            if let Some(err) = err.take() {
+                return Poll::Ready(Err(err));
+            }
+            if !sent {
+                ready!(nl.poll_send(cx, nlv))?;
+                sent = true;
+            }
+            loop {
+                // wait for the ACK without the handler after Stop or Err
+                let reply = match replies.next_reply(nl.get_ref().portid()) {
+                    Ok(reply) => reply,
+                    Err(err) => return Poll::Ready(handler.finish().and(Err(err))),
+                };
+                match reply {
+                    Reply::Msg(msg) => handler.call(msg.msghdr()),
+                    Reply::End => return Poll::Ready(handler.finish()),
+                    Reply::Empty => {
+                        let nrecv = ready!(nl.poll_recv(cx, replies.buf_mut()))?;
+                        replies.filled(nrecv);
+                    }
+                }
+            }
+        })
+    }
+
+    /// returns a `Stream` of the messages received, e.g. multicast events.
+    pub fn events(&self) -> EventStream<'_> {
+        EventStream {
+            nl: self,
+            replies: Replies::new(0, false),
+        }
+    }
+}
+
+/// A `Stream` of messages, returned by `AsyncSocket::events()`.
+///
+/// Control messages are not yielded except an error. The stream does not end
+/// by an error, e.g. `ENOBUFS` on overrun.
+pub struct EventStream<'a> {
+    nl: &'a AsyncSocket,
+    replies: Replies,
+}
+
+impl<'a> Stream for EventStream<'a> {
+    type Item = Result<OwnedMsg>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.replies.next_reply(this.nl.get_ref().portid()) {
+                Ok(Reply::Msg(msg)) => return Poll::Ready(Some(Ok(msg))),
+                Ok(Reply::End) => {}
+                Ok(Reply::Empty) => match ready!(this.nl.poll_recv(cx, this.replies.buf_mut())) {
+                    Ok(nrecv) => this.replies.filled(nrecv),
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                },
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
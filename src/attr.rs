@@ -1,6 +1,6 @@
-use std::{convert::TryFrom, fmt, marker::PhantomData, mem, slice, str};
+use std::{convert::TryFrom, fmt, marker::PhantomData, mem, ptr, slice, str};
 
-use crate::{AttrDataType, CbResult, CbStatus, GenError, Msghdr, Result};
+use crate::{AttrDataType, CbResult, CbStatus, Error, Msghdr, Policy, Result};
 use errno::Errno;
 use libc;
 
@@ -110,6 +110,39 @@ impl<'a> Attr<'a> {
             as *const Self)
     }
 
+    /// get an attribute from the head of a buffer
+    ///
+    /// This function returns a reference to the attribute placed at the
+    /// beginning of `buf`, after checking that the buffer is aligned to
+    /// `ALIGNTO` and has enough room for the whole attribute. `EINVAL` is
+    /// returned if the buffer is misaligned, `Error::Malformed` if the
+    /// attribute is malformed or truncated.
+    pub fn from_bytes(buf: &'a [u8]) -> Result<&'a Self> {
+        if buf.as_ptr() as usize & (crate::ALIGNTO - 1) != 0 {
+            return Err(Errno(libc::EINVAL).into());
+        }
+        if buf.len() < Self::HDRLEN {
+            return Err(Error::Malformed { offset: 0 });
+        }
+        let len = u16::from_ne_bytes([buf[0], buf[1]]) as usize;
+        if len < Self::HDRLEN || len > buf.len() {
+            return Err(Error::Malformed { offset: 0 });
+        }
+        Ok(unsafe { &*(buf.as_ptr() as *const Self) })
+    }
+
+    /// returns the whole attribute, header and payload, as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self as *const _ as *const u8, self.nla_len as usize) }
+    }
+
+    /// returns an iterator over the attributes nested in this one.
+    ///
+    /// @imitates: [libmnl::mnl_attr_for_each_nested]
+    pub fn nested(&'a self) -> Attrs<'a> {
+        Attrs::new(&self.as_bytes()[Self::HDRLEN..])
+    }
+
     /// check if the attribute type is valid.
     ///
     /// This function allows to check if the attribute type is higher than the
@@ -123,14 +156,14 @@ impl<'a> Attr<'a> {
     /// @imitates: [libmnl::mnl_attr_type_valid]
     pub fn type_valid(&self, max: u16) -> Result<()> {
         if self.atype() > max {
-            return Err(Errno(libc::EOPNOTSUPP));
+            return Err(Errno(libc::EOPNOTSUPP).into());
         }
         Ok(())
     }
 
     pub fn type_valid2(&self, max: impl Into<u16>) -> Result<()> {
         if self.atype() > max.into() {
-            return Err(Errno(libc::EOPNOTSUPP));
+            return Err(Errno(libc::EOPNOTSUPP).into());
         }
         Ok(())
     }
@@ -141,7 +174,7 @@ impl<'a> Attr<'a> {
 }
 
 /// @imitates: [mnl_attr_data_type_len]
-fn data_type_len(atype: AttrDataType) -> u16 {
+pub(crate) fn data_type_len(atype: AttrDataType) -> u16 {
     match atype {
         AttrDataType::U8 => mem::size_of::<u8>() as u16,
         AttrDataType::U16 => mem::size_of::<u16>() as u16,
@@ -158,39 +191,39 @@ impl<'a> Attr<'a> {
         let attr_len = self.payload_len();
 
         if attr_len < exp_len {
-            return Err(Errno(libc::ERANGE));
+            return Err(Errno(libc::ERANGE).into());
         }
         match atype {
             AttrDataType::Flag => {
                 if attr_len > 0 {
-                    return Err(Errno(libc::ERANGE));
+                    return Err(Errno(libc::ERANGE).into());
                 }
             }
             AttrDataType::NulString => {
                 if attr_len == 0 {
-                    return Err(Errno(libc::ERANGE));
+                    return Err(Errno(libc::ERANGE).into());
                 }
                 if unsafe {
                     *(self.payload_raw() as *const _ as *const u8).offset((attr_len - 1) as isize)
                         != 0
                 } {
-                    return Err(Errno(libc::EINVAL));
+                    return Err(Errno(libc::EINVAL).into());
                 }
             }
             AttrDataType::String => {
                 if attr_len == 0 {
-                    return Err(Errno(libc::ERANGE));
+                    return Err(Errno(libc::ERANGE).into());
                 }
             }
             AttrDataType::Nested => {
                 if attr_len != 0 && attr_len < Self::HDRLEN as u16 {
-                    return Err(Errno(libc::ERANGE));
+                    return Err(Errno(libc::ERANGE).into());
                 }
             }
             _ => {}
         }
         if exp_len != 0 && attr_len > exp_len {
-            return Err(Errno(libc::ERANGE));
+            return Err(Errno(libc::ERANGE).into());
         }
 
         Ok(())
@@ -217,33 +250,88 @@ impl<'a> Attr<'a> {
     }
 }
 
-/// A struct for nesteds `Attr` stream iterator.
+/// A struct for `Attr` stream iterator.
+///
+/// Walks over a sequence of attributes, either the payload of a netlink
+/// message or the payload of a nested attribute, by slicing the buffer so that
+/// each attribute is bounds checked before it is handed out. A malformed or
+/// truncated attribute is returned as `Err` and ends the iteration.
 ///
-/// @imitates: [libmnl::mnl_attr_for_each_nested]
-pub struct NestAttr<'a> {
-    head: &'a Attr<'a>,
-    cur: &'a Attr<'a>,
+/// ```
+/// let mut nlv = rsmnl::MsgVec::new();
+/// nlv.put_header();
+/// nlv.put(1u16, &0x11u8).unwrap();
+/// nlv.put(2u16, &0x12u8).unwrap();
+/// let types = nlv.msghdr().unwrap()
+///     .attrs(0)
+///     .map(|attr| attr.map(|attr| attr.atype()))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(types, [1, 2]);
+/// ```
+/// @imitates: [libmnl::mnl_attr_for_each, libmnl::mnl_attr_for_each_nested]
+pub struct Attrs<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    err: Option<Error>,
 }
 
-impl<'a> NestAttr<'a> {
-    pub fn new(attr: &'a Attr<'a>) -> Self {
+impl<'a> Attrs<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
         Self {
-            head: attr,
-            cur: unsafe { attr.payload_raw::<Attr>() },
+            buf,
+            offset: 0,
+            err: None,
         }
     }
 
-    pub fn next(&mut self) -> Option<&'a Attr<'a>> {
-        if self.cur.ok(
-            unsafe { self.head.payload_raw::<u8>() } as *const _ as isize
-                + self.head.payload_len() as isize
-                - self.cur as *const _ as isize,
-        ) {
-            let ret = Some(self.cur);
-            self.cur = unsafe { self.cur.next() };
-            ret
-        } else {
-            None
+    /// creates an iterator which yields only `err`.
+    pub(crate) fn failed(err: Error) -> Self {
+        Self {
+            buf: &[],
+            offset: 0,
+            err: Some(err),
+        }
+    }
+
+    /// returns the offset of the next attribute from the head of the buffer.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// returns the head of the buffer.
+    pub(crate) fn as_ptr(&self) -> *const u8 {
+        self.buf.as_ptr()
+    }
+}
+
+impl<'a> Iterator for Attrs<'a> {
+    type Item = Result<&'a Attr<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.err.take() {
+            return Some(Err(err));
+        }
+        let rest = &self.buf[self.offset..];
+        if rest.is_empty() {
+            return None;
+        }
+        match Attr::from_bytes(rest) {
+            Ok(attr) => {
+                let len = crate::align(attr.nla_len as usize);
+                self.offset += len.min(rest.len());
+                Some(Ok(attr))
+            }
+            Err(err) => {
+                let err = match err {
+                    Error::Malformed { .. } => Error::Malformed {
+                        offset: self.offset,
+                    },
+                    err => err,
+                };
+                self.offset = self.buf.len();
+                Some(Err(err))
+            }
         }
     }
 }
@@ -261,14 +349,13 @@ impl<'a> Attr<'a> {
         // validate AttrDataType::Nested
         let attr_len = self.payload_len();
         if attr_len != 0 && attr_len < Self::HDRLEN as u16 {
-            return crate::gen_errno!(libc::ERANGE);
+            return Err(Errno(libc::ERANGE).into());
         }
         // XXX: need check? - attr.nla_type & NLA_F_NESTED?
 
-        let mut ret: CbResult = crate::gen_errno!(libc::ENOENT);
-        let mut nested = NestAttr::new(self);
-        while let Some(attr) = nested.next() {
-            ret = cb(attr);
+        let mut ret: CbResult = Err(Errno(libc::ENOENT).into());
+        for attr in self.nested() {
+            ret = cb(attr?);
             match ret {
                 Ok(CbStatus::Ok) => {}
                 _ => return ret,
@@ -276,6 +363,24 @@ impl<'a> Attr<'a> {
         }
         ret
     }
+
+    /// parse attributes inside a nest with validation
+    ///
+    /// This function is like `parse_nested()` but all attributes inside the
+    /// nest are validated by `policy` before calling `cb`. If one of them does
+    /// not conform, `cb` is never called and the returned error is
+    /// `PolicyError`, which tells the attribute and the reason, with its
+    /// offset from this nest attribute.
+    ///
+    /// @imitates: [netlink::nla_parse_nested]
+    pub fn parse_nested_with_policy<T: FnMut(&'a Self) -> CbResult>(
+        &'a self,
+        policy: &Policy,
+        cb: T,
+    ) -> CbResult {
+        policy.check(self.as_intptr() as usize, self.nested(), &mut Vec::new())?;
+        self.parse_nested(cb)
+    }
 }
 
 impl<'a> Attr<'a> {
@@ -286,13 +391,23 @@ impl<'a> Attr<'a> {
     ///             libmnl::mnl_attr_get_u32,
     ///             libmnl::mnl_attr_get_u64]
     pub fn value<T: Copy>(&self) -> Result<T> {
-        Ok(*(self.value_ref::<T>()?))
+        if mem::size_of::<T>() > self.payload_len() as usize {
+            return Err(Errno(libc::ERANGE).into());
+        }
+        // payload is aligned only to ALIGNTO, u64 may not be aligned.
+        unsafe { Ok(ptr::read_unaligned(self.payload_ptr() as *const T)) }
     }
 
     /// returns attribute payload as a reference.
+    ///
+    /// EINVAL is returned if the payload is not aligned for `T`, use `value()`
+    /// for e.g. u64.
     pub fn value_ref<T>(&self) -> Result<&T> {
         if mem::size_of::<T>() > self.payload_len() as usize {
-            return Err(Errno(libc::ERANGE));
+            return Err(Errno(libc::ERANGE).into());
+        }
+        if unsafe { self.payload_ptr() } as usize & (mem::align_of::<T>() - 1) != 0 {
+            return Err(Errno(libc::EINVAL).into());
         }
         unsafe { Ok(self.payload_raw::<T>()) }
     }
@@ -306,11 +421,11 @@ impl<'a> Attr<'a> {
         // _validate AttrDataType::String
         let attr_len = self.payload_len() as usize;
         if attr_len == 0 {
-            return Err(Errno(libc::ERANGE));
+            return Err(Errno(libc::ERANGE).into());
         }
 
         let s = unsafe { slice::from_raw_parts(self.payload_ptr(), attr_len) };
-        str::from_utf8(s).map_err(|_| Errno(libc::EILSEQ))
+        str::from_utf8(s).map_err(|_| Errno(libc::EILSEQ).into())
     }
 
     pub fn cstr(&self) -> Result<&str> {
@@ -318,14 +433,14 @@ impl<'a> Attr<'a> {
         let pptr = unsafe { self.payload_ptr() };
         let attr_len = self.payload_len() as usize;
         if attr_len == 0 {
-            return Err(Errno(libc::ERANGE));
+            return Err(Errno(libc::ERANGE).into());
         }
         if unsafe { *pptr.offset((attr_len - 1) as isize) } != 0 {
-            return Err(Errno(libc::EINVAL));
+            return Err(Errno(libc::EINVAL).into());
         }
 
         let s = unsafe { slice::from_raw_parts(pptr, attr_len - 1) };
-        str::from_utf8(s).map_err(|_| Errno(libc::EILSEQ))
+        str::from_utf8(s).map_err(|_| Errno(libc::EILSEQ).into())
     }
 
     pub fn bytes_ref(&self) -> &[u8] {
@@ -341,20 +456,21 @@ pub trait AttrTbl<'a>: std::marker::Sized {
     fn new() -> Self;
     fn _set(&mut self, index: Self::Index, attr: &'a Attr);
 
+    /// validates all attributes in the table
+    ///
+    /// This function does nothing by default, since validation is done on
+    /// getting value. `#[derive(AttrTbl)]` overrides this to validate each
+    /// attribute by its data type, including nested tables.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
     fn try_from_nlmsg(offset: usize, nlh: &'a Msghdr) -> Result<Self> {
         let mut tb = Self::new();
         nlh.parse(offset, |attr: &Attr| {
             tb._set(Self::Index::try_from(attr.atype())?, attr);
             // tb[T::try_from(attr.atype())?] = Some(attr);
             Ok(CbStatus::Ok)
-        })
-        .map_err(|err| {
-            // Msghdr::parse() itself returns ENOENT only.
-            if let Some(e) = err.downcast_ref::<Errno>() {
-                *e
-            } else {
-                unreachable!()
-            }
         })?;
         Ok(tb)
     }
@@ -366,13 +482,6 @@ pub trait AttrTbl<'a>: std::marker::Sized {
             tb._set(Self::Index::try_from(attr.atype())?, attr);
             // tb[T::try_from(attr.atype())?] = Some(attr);
             Ok(CbStatus::Ok)
-        })
-        .map_err(|err| {
-            if let Some(e) = err.downcast_ref::<Errno>() {
-                *e
-            } else {
-                unreachable!()
-            }
         })?;
         Ok(tb)
     }
@@ -389,16 +498,9 @@ pub trait AttrTbl<'a>: std::marker::Sized {
     fn from_nlmsg(offset: usize, nlh: &'a Msghdr) -> Result<Self> {
         let mut tb = Self::new();
         let mut count = 0;
-        nlh.parse(offset, |attr: &Attr| tb.add(attr, &mut count))
-            .map_err(|err| {
-                if let Some(e) = err.downcast_ref::<Errno>() {
-                    *e
-                } else {
-                    unreachable!()
-                }
-            })?;
+        nlh.parse(offset, |attr: &Attr| tb.add(attr, &mut count))?;
         if count == 0 {
-            Err(Errno(libc::ENOENT))
+            Err(Errno(libc::ENOENT).into())
         } else {
             Ok(tb)
         }
@@ -408,16 +510,9 @@ pub trait AttrTbl<'a>: std::marker::Sized {
         nest.validate(crate::AttrDataType::Nested)?;
         let mut tb = Self::new();
         let mut count = 0;
-        nest.parse_nested(|attr: &Attr| tb.add(attr, &mut count))
-            .map_err(|err| {
-                if let Some(e) = err.downcast_ref::<Errno>() {
-                    *e
-                } else {
-                    unreachable!()
-                }
-            })?;
+        nest.parse_nested(|attr: &Attr| tb.add(attr, &mut count))?;
         if count == 0 {
-            Err(Errno(libc::ENOENT))
+            Err(Errno(libc::ENOENT).into())
         } else {
             Ok(tb)
         }
@@ -430,13 +525,6 @@ impl<'a> Attr<'a> {
         self.parse_nested(|nest| {
             v.push(T::from_nest(nest)?);
             Ok(CbStatus::Ok)
-        })
-        .map_err(|err| {
-            if let Some(e) = err.downcast_ref::<Errno>() {
-                *e
-            } else {
-                unreachable!()
-            }
         })?;
         Ok(v)
     }
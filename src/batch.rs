@@ -0,0 +1,257 @@
+use std::collections::{HashSet, VecDeque};
+
+use errno::Errno;
+use libc;
+use {Dispatcher, Error, Messages, MsgVec, Msghdr, RecvBuf, Result, Socket};
+
+/// A datagram of `BatchWriter`, and the sequence numbers of its messages.
+pub struct Chunk {
+    nlv: MsgVec,
+    seqs: Vec<u32>,
+}
+
+impl Chunk {
+    fn new(limit: usize) -> Self {
+        Self {
+            nlv: MsgVec::bounded(limit),
+            seqs: Vec::new(),
+        }
+    }
+
+    pub fn msgvec(&self) -> &MsgVec {
+        &self.nlv
+    }
+
+    /// returns the sequence numbers in the order of the messages.
+    pub fn seqs(&self) -> &[u32] {
+        &self.seqs
+    }
+}
+
+/// The ACKs gathered for a `Chunk` sent by `BatchWriter::send_next()`.
+#[derive(Debug)]
+pub struct ChunkAcks {
+    /// the sequence numbers of all the messages sent in the chunk.
+    pub seqs: Vec<u32>,
+    /// the results of the messages which have `NLM_F_ACK`, in the order of
+    /// arrival.
+    pub results: Vec<(u32, Result<()>)>,
+}
+
+impl ChunkAcks {
+    pub fn is_ok(&self) -> bool {
+        self.results.iter().all(|(_, res)| res.is_ok())
+    }
+
+    /// returns the messages failed, by their sequence number.
+    pub fn errors(&self) -> impl Iterator<Item = (u32, &Error)> {
+        self.results
+            .iter()
+            .filter_map(|(seq, res)| res.as_ref().err().map(|err| (*seq, err)))
+    }
+}
+
+// the chunk sent, whose ACKs are being received.
+struct Sent {
+    seqs: Vec<u32>,
+    // the messages with NLM_F_ACK not acknowledged yet
+    pending: HashSet<u32>,
+    results: Vec<(u32, Result<()>)>,
+}
+
+/// Cuts a stream of messages into datagrams of a limited size.
+///
+/// Messages are put as they are into the last chunk, or a new one if it
+/// has no room, so that a message is never split. Each chunk is sent as a
+/// datagram and its ACKs are received before the next one, then the replies
+/// do not overrun the receive buffer.
+///
+/// ```no_run
+/// # extern crate libc;
+/// # extern crate rsmnl as mnl;
+/// # use mnl::{BatchWriter, MsgVec, Socket};
+/// # fn main() -> mnl::Result<()> {
+/// let mut nl = Socket::open(libc::NETLINK_NETFILTER, 0)?;
+/// nl.bind(0, mnl::SOCKET_AUTOPID)?;
+/// let mut writer = BatchWriter::new(mnl::socket_buffer_size());
+/// let mut nlv = MsgVec::new();
+/// for _ in 0..1024 {
+///     nlv.reset();
+///     let nlh = nlv.put_header();
+///     // ... with NLM_F_ACK
+///     nlh.nlmsg_seq = nl.next_seq();
+///     writer.push(&nlv)?;
+///     // send the chunks filled
+///     while writer.chunks() > 1 {
+///         writer.send_next(&nl)?;
+///     }
+/// }
+/// for acks in writer.flush(&nl)? {
+///     for (seq, err) in acks.errors() {
+///         println!("message with seq {} has failed: {}", seq, err);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BatchWriter {
+    limit: usize,
+    chunks: VecDeque<Chunk>,
+    sent: Option<Sent>,
+}
+
+impl BatchWriter {
+    /// creates a writer whose datagrams are `limit` bytes at most.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            chunks: VecDeque::new(),
+            sent: None,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// returns the number of chunks not sent yet, including the last one
+    /// being filled.
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// returns true if no chunk is left to send, nor to receive the ACKs.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty() && self.sent.is_none()
+    }
+
+    pub fn chunk(&self, i: usize) -> Option<&Chunk> {
+        self.chunks.get(i)
+    }
+
+    /// returns the index of the chunk which has the message `seq`.
+    pub fn chunk_of(&self, seq: u32) -> Option<usize> {
+        self.chunks.iter().position(|c| c.seqs.contains(&seq))
+    }
+
+    // puts into the last chunk, or a new one if no room.
+    fn put(&mut self, nlh: &Msghdr) -> Result<()> {
+        if let Some(last) = self.chunks.back_mut() {
+            if last.nlv.put_msg(nlh).is_ok() {
+                last.seqs.push(nlh.nlmsg_seq);
+                return Ok(());
+            }
+        }
+        let mut chunk = Chunk::new(self.limit);
+        chunk.nlv.put_msg(nlh)?;
+        chunk.seqs.push(nlh.nlmsg_seq);
+        self.chunks.push_back(chunk);
+        Ok(())
+    }
+
+    /// appends the messages in `nlv`
+    ///
+    /// `EMSGSIZE` is returned if a message is larger than the limit, and
+    /// `EINPROGRESS` if `nlv` has a nest open. Nothing is appended on error.
+    pub fn push(&mut self, nlv: &MsgVec) -> Result<()> {
+        let msgs = Messages::new(nlv.finalize()?).collect::<Result<Vec<_>>>()?;
+        if msgs
+            .iter()
+            .any(|nlh| crate::align(nlh.nlmsg_len as usize) > self.limit)
+        {
+            return Err(Errno(libc::EMSGSIZE).into());
+        }
+        for nlh in msgs {
+            self.put(nlh)?;
+        }
+        Ok(())
+    }
+
+    /// sends the first chunk and receives its ACKs, or returns `None` if
+    /// there is no chunk
+    ///
+    /// This blocks on a blocking socket, and the socket should not be used
+    /// for the other requests or notifications meanwhile. Messages without
+    /// `NLM_F_ACK` are not waited for, so their errors are not reported.
+    ///
+    /// The chunk is kept to be sent again if sending fails. Once sent, it is
+    /// kept until all its ACKs are received, even if receiving fails, e.g. by
+    /// `EAGAIN`, and the next call resumes receiving instead of sending the
+    /// next one. If the ACKs are lost, e.g. by `ENOBUFS`, `unacked()` tells
+    /// the messages and `abandon()` gives them up.
+    pub fn send_next(&mut self, nl: &Socket) -> Result<Option<ChunkAcks>> {
+        if self.sent.is_none() {
+            let chunk = match self.chunks.front() {
+                Some(chunk) => chunk,
+                None => return Ok(None),
+            };
+            nl.sendto(&chunk.nlv)?;
+            let chunk = self.chunks.pop_front().unwrap();
+            let pending = Messages::new(chunk.nlv.as_ref())
+                .map_while(|nlh| nlh.ok())
+                .filter(|nlh| nlh.nlmsg_flags & libc::NLM_F_ACK as u16 != 0)
+                .map(|nlh| nlh.nlmsg_seq)
+                .collect();
+            self.sent = Some(Sent {
+                seqs: chunk.seqs,
+                pending,
+                results: Vec::new(),
+            });
+        }
+        let sent = self.sent.as_mut().unwrap();
+        let mut dispatcher = Dispatcher::new(nl.portid());
+        for seq in &sent.pending {
+            dispatcher.expect(*seq);
+        }
+        let mut buf = RecvBuf::new(crate::SOCKET_DUMP_SIZE);
+        while !dispatcher.is_done() {
+            let nrecv = nl.recvfrom(&mut buf)?;
+            for (seq, res) in dispatcher.run(&buf[..nrecv])? {
+                sent.pending.remove(&seq);
+                sent.results.push((seq, res));
+            }
+        }
+        Ok(self.abandon())
+    }
+
+    /// returns the messages of the chunk sent which have not been
+    /// acknowledged yet.
+    pub fn unacked(&self) -> Vec<u32> {
+        self.sent.as_ref().map_or_else(Vec::new, |sent| {
+            sent.seqs
+                .iter()
+                .filter(|seq| sent.pending.contains(seq))
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// stops receiving the ACKs of the chunk sent, and returns the ones
+    /// received so far.
+    ///
+    /// The ACKs arriving later are discarded by the next `send_next()`.
+    pub fn abandon(&mut self) -> Option<ChunkAcks> {
+        self.sent.take().map(|sent| ChunkAcks {
+            seqs: sent.seqs,
+            results: sent.results,
+        })
+    }
+
+    /// sends all the chunks by `send_next()`, and returns their ACKs.
+    ///
+    /// On `Err`, the ACKs of the chunks sent before are lost, while the one
+    /// being received is kept as `send_next()`. Call `send_next()` to handle
+    /// each.
+    pub fn flush(&mut self, nl: &Socket) -> Result<Vec<ChunkAcks>> {
+        let mut acks = Vec::with_capacity(self.chunks.len());
+        while let Some(chunk_acks) = self.send_next(nl)? {
+            acks.push(chunk_acks);
+        }
+        Ok(acks)
+    }
+
+    /// discards the chunks not sent.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+    }
+}
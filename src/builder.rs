@@ -0,0 +1,194 @@
+use libc::{self, c_int};
+use {Error, Result, Socket};
+
+type SetBool = fn(&Socket, bool) -> Result<()>;
+
+/// An option set by `SocketBuilder`, to tell which one was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SockOpt {
+    RcvBuf,
+    SndBuf,
+    StrictChk,
+    ExtAck,
+    CapAck,
+    NoEnobufs,
+    BroadcastError,
+    Pktinfo,
+    /// `NETLINK_ADD_MEMBERSHIP` of the group.
+    Membership(u32),
+}
+
+/// Opens a `Socket` and sets options in one place.
+///
+/// Options are set after the socket is opened and before it is bound, except
+/// the memberships which are joined after, not to be cleared by binding. An
+/// option the running kernel does not support, or the caller is not
+/// permitted, does not fail `build()` but is reported with its error.
+///
+/// ```
+/// # extern crate libc;
+/// # extern crate rsmnl as mnl;
+/// # fn main() -> mnl::Result<()> {
+/// let (nl, rejected) = mnl::SocketBuilder::new(libc::NETLINK_ROUTE)
+///     .cloexec()
+///     .rcvbuf(1 << 20)
+///     .ext_ack(true)
+///     .strict_chk(true)
+///     .bind(0, mnl::SOCKET_AUTOPID)
+///     .build()?;
+/// for (opt, err) in rejected {
+///     println!("{:?} is rejected: {}", opt, err);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SocketBuilder {
+    bus: c_int,
+    flags: c_int,
+    rcvbuf: Option<usize>,
+    sndbuf: Option<usize>,
+    strict_chk: Option<bool>,
+    ext_ack: Option<bool>,
+    cap_ack: Option<bool>,
+    no_enobufs: Option<bool>,
+    broadcast_error: Option<bool>,
+    pktinfo: Option<bool>,
+    groups: Vec<u32>,
+    bind: Option<(u32, u32)>,
+}
+
+impl SocketBuilder {
+    pub fn new<T: Into<c_int>>(bus: T) -> Self {
+        Self {
+            bus: bus.into(),
+            flags: 0,
+            rcvbuf: None,
+            sndbuf: None,
+            strict_chk: None,
+            ext_ack: None,
+            cap_ack: None,
+            no_enobufs: None,
+            broadcast_error: None,
+            pktinfo: None,
+            groups: Vec::new(),
+            bind: None,
+        }
+    }
+
+    /// opens with `SOCK_CLOEXEC`.
+    pub fn cloexec(mut self) -> Self {
+        self.flags |= libc::SOCK_CLOEXEC;
+        self
+    }
+
+    /// opens with `SOCK_NONBLOCK`.
+    pub fn nonblock(mut self) -> Self {
+        self.flags |= libc::SOCK_NONBLOCK;
+        self
+    }
+
+    /// sets the receive buffer size by `Socket::set_rcvbuf_force()`, which
+    /// falls back to `SO_RCVBUF` without `CAP_NET_ADMIN`.
+    pub fn rcvbuf(mut self, size: usize) -> Self {
+        self.rcvbuf = Some(size);
+        self
+    }
+
+    pub fn sndbuf(mut self, size: usize) -> Self {
+        self.sndbuf = Some(size);
+        self
+    }
+
+    pub fn strict_chk(mut self, v: bool) -> Self {
+        self.strict_chk = Some(v);
+        self
+    }
+
+    pub fn ext_ack(mut self, v: bool) -> Self {
+        self.ext_ack = Some(v);
+        self
+    }
+
+    pub fn cap_ack(mut self, v: bool) -> Self {
+        self.cap_ack = Some(v);
+        self
+    }
+
+    pub fn no_enobufs(mut self, v: bool) -> Self {
+        self.no_enobufs = Some(v);
+        self
+    }
+
+    pub fn broadcast_error(mut self, v: bool) -> Self {
+        self.broadcast_error = Some(v);
+        self
+    }
+
+    pub fn pktinfo(mut self, v: bool) -> Self {
+        self.pktinfo = Some(v);
+        self
+    }
+
+    /// joins the multicast `group` after binding, not the bitmask of
+    /// `bind()`.
+    pub fn membership(mut self, group: u32) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// binds after setting the options, as `Socket::bind()`.
+    pub fn bind(mut self, groups: u32, pid: u32) -> Self {
+        self.bind = Some((groups, pid));
+        self
+    }
+
+    /// opens the socket, sets the options and binds
+    ///
+    /// This returns the socket with the options rejected. `Err` is returned
+    /// only if opening or binding fails.
+    pub fn build(&self) -> Result<(Socket, Vec<(SockOpt, Error)>)> {
+        let mut nl = Socket::open(self.bus, self.flags as u32)?;
+        let mut rejected = Vec::new();
+        {
+            let mut check = |opt, res: Result<()>| {
+                if let Err(err) = res {
+                    rejected.push((opt, err));
+                }
+            };
+            if let Some(size) = self.rcvbuf {
+                check(SockOpt::RcvBuf, nl.set_rcvbuf_force(size));
+            }
+            if let Some(size) = self.sndbuf {
+                check(SockOpt::SndBuf, nl.set_sndbuf(size));
+            }
+            let bools: [(SockOpt, Option<bool>, SetBool); 6] = [
+                (SockOpt::StrictChk, self.strict_chk, Socket::set_strict_chk),
+                (SockOpt::ExtAck, self.ext_ack, Socket::set_ext_ack),
+                (SockOpt::CapAck, self.cap_ack, Socket::set_cap_ack),
+                (SockOpt::NoEnobufs, self.no_enobufs, Socket::set_no_enobufs),
+                (
+                    SockOpt::BroadcastError,
+                    self.broadcast_error,
+                    Socket::set_broadcast_error,
+                ),
+                (SockOpt::Pktinfo, self.pktinfo, Socket::set_pktinfo),
+            ];
+            for &(opt, v, set) in bools.iter() {
+                if let Some(v) = v {
+                    check(opt, set(&nl, v));
+                }
+            }
+        }
+        // bind overwrites the first 32 groups by its bitmask
+        if let Some((groups, pid)) = self.bind {
+            nl.bind(groups, pid)?;
+        }
+        for &group in &self.groups {
+            if let Err(err) = nl.add_membership(group) {
+                rejected.push((SockOpt::Membership(group), err));
+            }
+        }
+        Ok((nl, rejected))
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{CbResult, CbStatus, GenError, Msghdr};
+use crate::{CbResult, CbStatus, Error, KernelError, Messages, Msghdr};
 use errno::Errno;
 use libc::{self, nlmsgerr};
 
@@ -10,11 +10,10 @@ fn noop(_nlh: &Msghdr) -> CbResult {
 
 fn error(nlh: &Msghdr) -> CbResult {
     let err = nlh.payload::<nlmsgerr>()?;
-    match err.error {
-        e if e < 0 => crate::gen_errno!(-err.error),
-        e if e > 0 => crate::gen_errno!(err.error),
-        _ => Ok(CbStatus::Stop),
+    if err.error == 0 {
+        return Ok(CbStatus::Stop);
     }
+    Err(KernelError::from_nlmsg(nlh)?.into())
 }
 
 fn stop(_nlh: &Msghdr) -> CbResult {
@@ -53,42 +52,40 @@ where
     T: FnMut(&'a Msghdr<'a>) -> CbResult,
     U: FnMut(&'a Msghdr<'a>) -> CbResult,
 {
-    let mut nlh = unsafe { &*(buf.as_ptr() as *const _ as *const Msghdr) };
-    let mut len = buf.len() as isize;
-    if !nlh.ok(len) {
-        return crate::gen_errno!(libc::EBADMSG);
+    if buf.is_empty() {
+        return Err(Error::Malformed { offset: 0 });
+    }
+    if buf.as_ptr() as usize & (crate::ALIGNTO - 1) != 0 {
+        return Err(Errno(libc::EINVAL).into());
     }
 
-    loop {
+    for nlh in Messages::new(buf) {
+        let nlh = nlh?;
         nlh.portid_ok(portid)?;
         nlh.seq_ok(seq)?;
         // dump was interrupted
         if nlh.nlmsg_flags & libc::NLM_F_DUMP_INTR as u16 != 0 {
-            return crate::gen_errno!(libc::EINTR);
+            return Err(Error::DumpInterrupted);
         }
-        if nlh.nlmsg_type >= libc::NLMSG_MIN_TYPE as u16 {
-            if let Some(ref mut cb) = cb_data {
-                match cb(&nlh) {
-                    Ok(CbStatus::Ok) => {}
-                    ret @ _ => return ret,
-                }
+        let ret = if nlh.nlmsg_type >= libc::NLMSG_MIN_TYPE as u16 {
+            match cb_data {
+                Some(ref mut cb) => cb(nlh),
+                None => continue,
             }
         } else if nlh.nlmsg_type < cb_ctl_array.len() as u16 {
-            if let Some(ref mut ctl_cb) = cb_ctl_array[nlh.nlmsg_type as usize] {
-                match ctl_cb(&nlh) {
-                    Ok(CbStatus::Ok) => {}
-                    ret @ _ => return ret,
-                }
+            match cb_ctl_array[nlh.nlmsg_type as usize] {
+                Some(ref mut ctl_cb) => ctl_cb(nlh),
+                None => continue,
             }
-        } else if let Some(default_cb) = DEFAULT_CB_ARRAY[nlh.nlmsg_type as usize] {
-            match default_cb(&nlh) {
-                Ok(CbStatus::Ok) => {}
-                ret @ _ => return ret,
+        } else {
+            match DEFAULT_CB_ARRAY[nlh.nlmsg_type as usize] {
+                Some(default_cb) => default_cb(nlh),
+                None => continue,
             }
-        }
-        nlh = unsafe { nlh.next(&mut len) };
-        if !nlh.ok(len) {
-            break;
+        };
+        match ret {
+            Ok(CbStatus::Ok) => {}
+            _ => return ret,
         }
     }
     Ok(CbStatus::Ok)
@@ -104,10 +101,14 @@ where
 /// 	- MNL_CB_OK (>=1): no problem has occurred.
 ///
 /// This function propagates the callback return value. On error, it returns
-/// -1 and errno is explicitly set. If the portID is not the expected, errno
-/// is set to ESRCH. If the sequence number is not the expected, errno is set
-/// to EPROTO. If the dump was interrupted, errno is set to EINTR and you should
-/// request a new fresh dump again.
+/// `Error`. If the portID is not the expected, it is `PortidMismatch`. If the
+/// sequence number is not the expected, it is `SeqMismatch`. If the dump was
+/// interrupted, it is `DumpInterrupted` and you should request a new fresh dump
+/// again, which `Socket::dump_retry()` does. If a message in buf is
+/// truncated, it is `Malformed`, and EINVAL if buf is not aligned to
+/// `ALIGNTO`. An error message from the kernel is
+/// returned as `Kernel`, which has the extended ACK attributes if
+/// `Socket::set_ext_ack()` is enabled.
 ///
 /// @imitates: [libmnl::mnl_cb_run2]
 pub fn run2<T, U>(
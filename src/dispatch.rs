@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use errno::Errno;
+use libc::{self, c_int, nlmsgerr};
+use {CbResult, CbStatus, Error, KernelError, Messages, MsgVec, Msghdr, RecvBuf, Result, Socket};
+
+type Handler<'h> = Box<dyn FnMut(&Msghdr) -> CbResult + 'h>;
+
+struct Pending<'h> {
+    handler: Option<Handler<'h>>,
+    // the handler returned Stop, or failed
+    stopped: bool,
+    error: Option<Error>,
+}
+
+/// routes received messages to the handler registered for their sequence
+/// number.
+///
+/// Each registered request is pending until its `NLMSG_DONE` or ACK arrives,
+/// so that requests must have `NLM_F_ACK` or `NLM_F_DUMP`. Completed requests
+/// are reported with their own result, then a batch can tell which message has
+/// failed.
+///
+/// ```no_run
+/// # extern crate libc;
+/// # extern crate rsmnl as mnl;
+/// # use mnl::{Dispatcher, MsgVec, Socket};
+/// # fn main() -> mnl::Result<()> {
+/// let mut nl = Socket::open(libc::NETLINK_ROUTE, 0)?;
+/// nl.bind(0, mnl::SOCKET_AUTOPID)?;
+/// let mut nlv = MsgVec::new();
+/// // ... put requests with nl.next_seq() and NLM_F_ACK
+/// let mut dispatcher = Dispatcher::new(nl.portid());
+/// dispatcher.expect_batch(&nlv);
+/// nl.sendto(&nlv)?;
+/// for (seq, res) in dispatcher.recv_all(&nl)? {
+///     if let Err(err) = res {
+///         println!("message with seq {} has failed: {}", seq, err);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Dispatcher<'h> {
+    portid: u32,
+    pending: HashMap<u32, Pending<'h>>,
+    unsolicited: Option<Handler<'h>>,
+}
+
+impl<'h> Dispatcher<'h> {
+    pub fn new(portid: u32) -> Self {
+        Self {
+            portid,
+            pending: HashMap::new(),
+            unsolicited: None,
+        }
+    }
+
+    fn insert(&mut self, seq: u32, handler: Option<Handler<'h>>) {
+        self.pending.insert(
+            seq,
+            Pending {
+                handler,
+                stopped: false,
+                error: None,
+            },
+        );
+    }
+
+    /// registers `handler` for the data messages of the request `seq`.
+    ///
+    /// The handler is not called any more after it returns `Stop` or `Err`,
+    /// but the request is pending until it completes.
+    pub fn register<F: FnMut(&Msghdr) -> CbResult + 'h>(&mut self, seq: u32, handler: F) {
+        self.insert(seq, Some(Box::new(handler)));
+    }
+
+    /// waits for the ACK of the request `seq`, without a handler.
+    pub fn expect(&mut self, seq: u32) {
+        self.insert(seq, None);
+    }
+
+    /// waits for the ACK of each message in `nlv` which has `NLM_F_ACK`.
+    pub fn expect_batch(&mut self, nlv: &MsgVec) {
+        for nlh in Messages::new(nlv.as_ref()).map_while(|nlh| nlh.ok()) {
+            if nlh.nlmsg_flags & libc::NLM_F_ACK as u16 != 0 {
+                self.expect(nlh.nlmsg_seq);
+            }
+        }
+    }
+
+    /// sets the handler for the messages no request is waiting for, e.g.
+    /// multicast notifications. They are discarded if not set.
+    pub fn set_unsolicited<F: FnMut(&Msghdr) -> CbResult + 'h>(&mut self, handler: F) {
+        self.unsolicited = Some(Box::new(handler));
+    }
+
+    pub fn is_pending(&self, seq: u32) -> bool {
+        self.pending.contains_key(&seq)
+    }
+
+    /// returns the number of requests still waiting for ACK or `NLMSG_DONE`.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// dispatches messages in `buf`, and returns the sequence numbers and
+    /// results of the requests completed in it, in the order of arrival.
+    ///
+    /// `Err` is returned for the buffer itself, e.g. malformed message, portid
+    /// mismatch or an error of the unsolicited handler.
+    pub fn run(&mut self, buf: &[u8]) -> Result<Vec<(u32, Result<()>)>> {
+        let mut completed = Vec::new();
+        for nlh in Messages::new(buf) {
+            let nlh = nlh?;
+            nlh.portid_ok(self.portid)?;
+            let seq = nlh.nlmsg_seq;
+            let done = match self.pending.get_mut(&seq) {
+                Some(req) => Self::dispatch(req, nlh)?,
+                None => {
+                    if let Some(ref mut handler) = self.unsolicited {
+                        handler(nlh)?;
+                    }
+                    continue;
+                }
+            };
+            if let Some(res) = done {
+                let req = self.pending.remove(&seq).unwrap();
+                completed.push((seq, req.error.map_or(res, Err)));
+            }
+        }
+        Ok(completed)
+    }
+
+    // returns the result if the request has completed.
+    fn dispatch(req: &mut Pending<'h>, nlh: &Msghdr) -> Result<Option<Result<()>>> {
+        if nlh.nlmsg_flags & libc::NLM_F_DUMP_INTR as u16 != 0 && req.error.is_none() {
+            req.stopped = true;
+            req.error = Some(Error::DumpInterrupted);
+        }
+        match nlh.nlmsg_type as c_int {
+            libc::NLMSG_DONE => {
+                // may carry an error code as int
+                if let Ok(err) = nlh.payload::<c_int>() {
+                    if *err < 0 {
+                        return Ok(Some(Err(Errno(-*err).into())));
+                    }
+                }
+                Ok(Some(Ok(())))
+            }
+            libc::NLMSG_ERROR => {
+                if nlh.payload::<nlmsgerr>()?.error != 0 {
+                    Ok(Some(Err(KernelError::from_nlmsg(nlh)?.into())))
+                } else {
+                    Ok(Some(Ok(())))
+                }
+            }
+            t if t >= libc::NLMSG_MIN_TYPE => {
+                if req.stopped {
+                    return Ok(None);
+                }
+                if let Some(ref mut handler) = req.handler {
+                    match handler(nlh) {
+                        Ok(CbStatus::Ok) => {}
+                        Ok(CbStatus::Stop) => req.stopped = true,
+                        Err(err) => {
+                            req.stopped = true;
+                            req.error = Some(err);
+                        }
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// receives from `nl` and dispatches until no request is pending.
+    ///
+    /// This blocks on a blocking socket. For a non-blocking one, receive and
+    /// call `run()` by yourself.
+    pub fn recv_all(&mut self, nl: &Socket) -> Result<Vec<(u32, Result<()>)>> {
+        let mut buf = RecvBuf::new(crate::SOCKET_DUMP_SIZE);
+        let mut completed = Vec::new();
+        while !self.is_done() {
+            let nrecv = nl.recvfrom(&mut buf)?;
+            completed.extend(self.run(&buf[..nrecv])?);
+        }
+        Ok(completed)
+    }
+}
@@ -0,0 +1,152 @@
+use std::{error, fmt, io};
+
+use crate::{KernelError, PolicyError};
+use errno::Errno;
+use libc;
+
+/// The error type of this crate.
+///
+/// Each variant owns its context, so that it can be logged after the buffer
+/// the error came from is reused.
+#[derive(Debug)]
+pub enum Error {
+    /// an error from syscall, or an invalid argument.
+    Os(Errno),
+    /// truncated or malformed message or attribute, `offset` is from the head
+    /// of the buffer being parsed.
+    Malformed { offset: usize },
+    /// `Msghdr::seq_ok()` failed.
+    SeqMismatch { expected: u32, actual: u32 },
+    /// `Msghdr::portid_ok()` failed.
+    PortidMismatch { expected: u32, actual: u32 },
+    /// the message has `NLM_F_DUMP_INTR`, dump should be requested again.
+    DumpInterrupted,
+    /// `Socket::dump_retry()` gave up, every one of `attempts` dumps was
+    /// interrupted.
+    DumpRetryExhausted { attempts: u32 },
+    /// `NLMSG_ERROR` from the kernel.
+    Kernel(Box<KernelError>),
+    /// the received message is truncated, buffer of `capacity` bytes is too
+    /// small.
+    Truncated { capacity: usize },
+    /// an attribute does not conform to the policy.
+    Policy(PolicyError),
+    /// an error returned by user callback.
+    Callback(Box<dyn error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// wraps an error of user callback.
+    pub fn callback<E: Into<Box<dyn error::Error + Send + Sync>>>(err: E) -> Self {
+        Error::Callback(err.into())
+    }
+
+    /// returns the errno which libmnl would set for this error.
+    ///
+    /// `None` for `Callback` unless it is an `Errno` or OS `io::Error`.
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            Error::Os(errno) => Some(*errno),
+            Error::Malformed { .. } => Some(Errno(libc::EBADMSG)),
+            Error::SeqMismatch { .. } => Some(Errno(libc::EPROTO)),
+            Error::PortidMismatch { .. } => Some(Errno(libc::ESRCH)),
+            Error::DumpInterrupted => Some(Errno(libc::EINTR)),
+            Error::DumpRetryExhausted { .. } => Some(Errno(libc::EINTR)),
+            Error::Kernel(err) => Some(err.errno),
+            Error::Truncated { .. } => Some(Errno(libc::ENOSPC)),
+            Error::Policy(err) => Some(err.errno()),
+            Error::Callback(err) => {
+                if let Some(errno) = err.downcast_ref::<Errno>() {
+                    Some(*errno)
+                } else {
+                    err.downcast_ref::<io::Error>()
+                        .and_then(|e| e.raw_os_error())
+                        .map(Errno)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Os(errno) => write!(f, "{}", errno),
+            Error::Malformed { offset } => write!(f, "malformed message at offset {}", offset),
+            Error::SeqMismatch { expected, actual } => write!(
+                f,
+                "sequence number mismatch, expected {} but {}",
+                expected, actual
+            ),
+            Error::PortidMismatch { expected, actual } => {
+                write!(f, "portid mismatch, expected {} but {}", expected, actual)
+            }
+            Error::DumpInterrupted => write!(f, "dump was interrupted"),
+            Error::DumpRetryExhausted { attempts } => {
+                write!(f, "dump was interrupted {} times", attempts)
+            }
+            Error::Kernel(err) => write!(f, "{}", err),
+            Error::Truncated { capacity } => {
+                write!(f, "message truncated, buffer of {} bytes", capacity)
+            }
+            Error::Policy(err) => write!(f, "{}", err),
+            Error::Callback(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Kernel(err) => Some(err.as_ref()),
+            Error::Policy(err) => Some(err),
+            Error::Callback(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<Errno> for Error {
+    fn from(errno: Errno) -> Self {
+        Error::Os(errno)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.raw_os_error() {
+            Some(errno) => Error::Os(Errno(errno)),
+            None => Error::Callback(Box::new(err)),
+        }
+    }
+}
+
+impl From<KernelError> for Error {
+    fn from(err: KernelError) -> Self {
+        Error::Kernel(Box::new(err))
+    }
+}
+
+impl From<PolicyError> for Error {
+    fn from(err: PolicyError) -> Self {
+        Error::Policy(err)
+    }
+}
+
+impl From<Box<dyn error::Error + Send + Sync>> for Error {
+    fn from(err: Box<dyn error::Error + Send + Sync>) -> Self {
+        Error::Callback(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Os(errno) => io::Error::from_raw_os_error(errno.0),
+            err => match err.errno() {
+                Some(errno) => io::Error::new(io::Error::from_raw_os_error(errno.0).kind(), err),
+                None => io::Error::other(err),
+            },
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::{convert::TryInto, error::Error, fmt};
+
+use crate::{Attr, MsgVec, Msghdr, Result};
+use errno::Errno;
+use libc::{self, nlmsgerr};
+
+// linux/netlink.h
+const NLM_F_CAPPED: u16 = 0x100;
+const NLM_F_ACK_TLVS: u16 = 0x200;
+
+// enum nlmsgerr_attrs
+const NLMSGERR_ATTR_MSG: u16 = 1;
+const NLMSGERR_ATTR_OFFS: u16 = 2;
+const NLMSGERR_ATTR_COOKIE: u16 = 3;
+const NLMSGERR_ATTR_POLICY: u16 = 4;
+const NLMSGERR_ATTR_MISS_TYPE: u16 = 5;
+const NLMSGERR_ATTR_MISS_NEST: u16 = 6;
+
+// enum netlink_policy_type_attr
+const NL_POLICY_TYPE_ATTR_TYPE: u16 = 1;
+const NL_POLICY_TYPE_ATTR_MIN_VALUE_S: u16 = 2;
+const NL_POLICY_TYPE_ATTR_MAX_VALUE_S: u16 = 3;
+const NL_POLICY_TYPE_ATTR_MIN_VALUE_U: u16 = 4;
+const NL_POLICY_TYPE_ATTR_MAX_VALUE_U: u16 = 5;
+const NL_POLICY_TYPE_ATTR_MIN_LENGTH: u16 = 6;
+const NL_POLICY_TYPE_ATTR_MAX_LENGTH: u16 = 7;
+const NL_POLICY_TYPE_ATTR_MASK: u16 = 12;
+
+// 8 bytes integers are aligned to 4, not to read them via a reference.
+fn attr_u64(attr: &Attr) -> Result<u64> {
+    attr.as_bytes()[Attr::HDRLEN..]
+        .try_into()
+        .map(u64::from_ne_bytes)
+        .map_err(|_| Errno(libc::ERANGE).into())
+}
+
+/// policy of the attribute which the kernel rejected, `NLMSGERR_ATTR_POLICY`.
+///
+/// `atype` is `enum netlink_attribute_type` in linux/netlink.h.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtAckPolicy {
+    pub atype: Option<u32>,
+    pub min_value_s: Option<i64>,
+    pub max_value_s: Option<i64>,
+    pub min_value_u: Option<u64>,
+    pub max_value_u: Option<u64>,
+    pub min_length: Option<u32>,
+    pub max_length: Option<u32>,
+    pub mask: Option<u64>,
+}
+
+impl ExtAckPolicy {
+    fn parse(nest: &Attr) -> Result<Self> {
+        let mut policy = Self::default();
+        for attr in nest.nested() {
+            let attr = attr?;
+            match attr.atype() {
+                NL_POLICY_TYPE_ATTR_TYPE => policy.atype = Some(attr.value()?),
+                NL_POLICY_TYPE_ATTR_MIN_VALUE_S => {
+                    policy.min_value_s = Some(attr_u64(attr)? as i64)
+                }
+                NL_POLICY_TYPE_ATTR_MAX_VALUE_S => {
+                    policy.max_value_s = Some(attr_u64(attr)? as i64)
+                }
+                NL_POLICY_TYPE_ATTR_MIN_VALUE_U => policy.min_value_u = Some(attr_u64(attr)?),
+                NL_POLICY_TYPE_ATTR_MAX_VALUE_U => policy.max_value_u = Some(attr_u64(attr)?),
+                NL_POLICY_TYPE_ATTR_MIN_LENGTH => policy.min_length = Some(attr.value()?),
+                NL_POLICY_TYPE_ATTR_MAX_LENGTH => policy.max_length = Some(attr.value()?),
+                NL_POLICY_TYPE_ATTR_MASK => policy.mask = Some(attr_u64(attr)?),
+                _ => {}
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// extended ACK attributes which follow `struct nlmsgerr`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtAck {
+    /// human readable error message, `NLMSGERR_ATTR_MSG`.
+    pub msg: Option<String>,
+    /// offset of the invalid attribute in the request, `NLMSGERR_ATTR_OFFS`.
+    pub offset: Option<u32>,
+    pub cookie: Option<Vec<u8>>,
+    pub policy: Option<ExtAckPolicy>,
+    /// type of the missing attribute, `NLMSGERR_ATTR_MISS_TYPE`.
+    pub miss_type: Option<u32>,
+    /// offset of the nest the missing attribute should have been in,
+    /// `NLMSGERR_ATTR_MISS_NEST`.
+    pub miss_nest: Option<u32>,
+}
+
+impl ExtAck {
+    /// parses extended ACK attributes of `NLMSG_ERROR` message.
+    ///
+    /// It can be called for an ACK too, which may carry a warning message. If
+    /// the message has no `NLM_F_ACK_TLVS` flag, all fields are `None`.
+    pub fn parse(nlh: &Msghdr) -> Result<Self> {
+        let mut ext_ack = Self::default();
+        if nlh.nlmsg_flags & NLM_F_ACK_TLVS == 0 {
+            return Ok(ext_ack);
+        }
+        for attr in nlh.attrs(Self::tlv_offset(nlh)?) {
+            let attr = attr?;
+            match attr.atype() {
+                NLMSGERR_ATTR_MSG => ext_ack.msg = Some(attr.cstr()?.to_string()),
+                NLMSGERR_ATTR_OFFS => ext_ack.offset = Some(attr.value()?),
+                NLMSGERR_ATTR_COOKIE => {
+                    ext_ack.cookie = Some(attr.as_bytes()[Attr::HDRLEN..].to_vec())
+                }
+                NLMSGERR_ATTR_POLICY => ext_ack.policy = Some(ExtAckPolicy::parse(attr)?),
+                NLMSGERR_ATTR_MISS_TYPE => ext_ack.miss_type = Some(attr.value()?),
+                NLMSGERR_ATTR_MISS_NEST => ext_ack.miss_nest = Some(attr.value()?),
+                _ => {}
+            }
+        }
+        Ok(ext_ack)
+    }
+
+    // offset of the TLVs from the payload head, which is the size of
+    // nlmsgerr.error and the original request, the header only if capped.
+    fn tlv_offset(nlh: &Msghdr) -> Result<usize> {
+        let err = nlh.payload::<nlmsgerr>()?;
+        let req_len = if nlh.nlmsg_flags & NLM_F_CAPPED != 0 {
+            Msghdr::HDRLEN
+        } else {
+            err.msg.nlmsg_len as usize
+        };
+        Ok(std::mem::size_of::<libc::c_int>() + req_len)
+    }
+}
+
+/// An error the kernel reported by `NLMSG_ERROR` message.
+///
+/// This owns all its contents, so that it can be logged after the receive
+/// buffer is reused. `Display` shows the message from the kernel if any, like
+/// iproute2 does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelError {
+    pub errno: Errno,
+    /// the request the kernel echoes back, only the header if it was capped.
+    pub request: Vec<u8>,
+    pub ext_ack: ExtAck,
+}
+
+impl KernelError {
+    /// creates from `NLMSG_ERROR` message.
+    ///
+    /// Malformed extended ACK attributes are ignored, since the error code is
+    /// what matters.
+    pub fn from_nlmsg(nlh: &Msghdr) -> Result<Self> {
+        let err = nlh.payload::<nlmsgerr>()?;
+        let payload = &nlh.as_bytes()[Msghdr::HDRLEN..];
+        let start = std::mem::size_of::<libc::c_int>();
+        let end = ExtAck::tlv_offset(nlh)?.min(payload.len());
+        Ok(Self {
+            errno: Errno(err.error.abs()),
+            request: payload[start.min(end)..end].to_vec(),
+            ext_ack: ExtAck::parse(nlh).unwrap_or_default(),
+        })
+    }
+}
+
+impl KernelError {
+    /// returns the sequence number of the request.
+    pub fn seq(&self) -> Option<u32> {
+        self.request
+            .get(8..12)
+            .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+    }
+
+    /// resolves `ExtAck::offset` to the attribute path in the request
+    ///
+    /// The request is looked up from `nlv` by the sequence number, so that
+    /// `nlv` should be the one which was sent, or its recorded copy. `offset`
+    /// is the size of the extra header as `Msghdr::parse()`.
+    pub fn attr_path<'a>(&self, nlv: &'a MsgVec, offset: usize) -> Option<Vec<&'a Attr<'a>>> {
+        let attr_offset = self.ext_ack.offset? as usize;
+        nlv.find(self.seq()?)?.attr_path(offset, attr_offset)
+    }
+}
+
+impl fmt::Display for KernelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.ext_ack.msg {
+            Some(ref msg) => write!(f, "{}: {}", msg, self.errno),
+            None => write!(f, "{}", self.errno),
+        }
+    }
+}
+
+impl Error for KernelError {}
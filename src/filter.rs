@@ -0,0 +1,257 @@
+use std::convert::TryFrom;
+
+use errno::Errno;
+use libc::{self, c_int, sock_filter, sock_fprog};
+use {Msghdr, Result, Socket};
+
+// linux/filter.h and asm-generic/socket.h, not in libc for all the versions
+const SO_ATTACH_FILTER: c_int = 26;
+const SO_DETACH_FILTER: c_int = 27;
+const SO_LOCK_FILTER: c_int = 44;
+
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+const BPF_W: u16 = 0x00;
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_IND: u16 = 0x40;
+const BPF_JEQ: u16 = 0x10;
+const BPF_K: u16 = 0x00;
+const BPF_TAX: u16 = 0x00;
+// A = offset of the attribute whose type is X, searched from A
+const SKF_AD_NLATTR: u32 = (-0x1000 + 12) as u32;
+
+const NLMSG_TYPE_OFFSET: u32 = 4;
+
+impl Socket {
+    /// attach a classic BPF program to filter the received datagrams
+    ///
+    /// The program returns the number of bytes to keep, zero drops the
+    /// datagram in the kernel. See `Filter` for netlink predicates.
+    pub fn attach_filter(&self, prog: &[sock_filter]) -> Result<()> {
+        let fprog = sock_fprog {
+            len: u16::try_from(prog.len()).map_err(|_| Errno(libc::EINVAL))?,
+            filter: prog.as_ptr() as *mut _,
+        };
+        unsafe { self.setsockopt_level(libc::SOL_SOCKET, SO_ATTACH_FILTER, &fprog) }
+    }
+
+    pub fn detach_filter(&self) -> Result<()> {
+        unsafe { self.setsockopt_level(libc::SOL_SOCKET, SO_DETACH_FILTER, &(0 as c_int)) }
+    }
+
+    /// prevents the filter from being detached or replaced.
+    pub fn lock_filter(&self) -> Result<()> {
+        unsafe { self.setsockopt_level(libc::SOL_SOCKET, SO_LOCK_FILTER, &(1 as c_int)) }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Next,
+    Accept,
+    Reject,
+    // relative to the next instruction
+    Skip(usize),
+}
+
+/// A builder of a classic BPF program matching netlink messages.
+///
+/// A message is accepted if all the predicates match. Control messages, e.g.
+/// `NLMSG_ERROR` and `NLMSG_DONE`, are always accepted. The program looks at
+/// the first message of a datagram only, so that it suits the sockets
+/// receiving notifications, which are sent one by one.
+///
+/// Values are given as they are in the message, e.g. `0x1234u16.to_be()`
+/// for a big endian field.
+///
+/// ```
+/// # extern crate libc;
+/// # extern crate rsmnl as mnl;
+/// # fn main() -> mnl::Result<()> {
+/// // RTM_NEWLINK or RTM_DELLINK of AF_UNSPEC, having IFLA_IFNAME
+/// let prog = mnl::Filter::new()
+///     .nlmsg_type(&[libc::RTM_NEWLINK, libc::RTM_DELLINK])
+///     .header_u8(0, libc::AF_UNSPEC as u8)
+///     .attr(16, libc::IFLA_IFNAME)
+///     .build()?;
+/// let nl = mnl::Socket::open(libc::NETLINK_ROUTE, 0)?;
+/// nl.attach_filter(&prog)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    insns: Vec<(u16, u32, Target, Target)>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self { insns: Vec::new() }
+    }
+
+    fn stmt(&mut self, code: u16, k: u32) {
+        self.insns.push((code, k, Target::Next, Target::Next));
+    }
+
+    fn jump(&mut self, code: u16, k: u32, jt: Target, jf: Target) {
+        self.insns.push((code, k, jt, jf));
+    }
+
+    // compare A with the value loaded in the same byte order as the message
+    fn expect(&mut self, v: u32) {
+        self.jump(BPF_JMP | BPF_JEQ | BPF_K, v, Target::Next, Target::Reject);
+    }
+
+    /// accepts messages whose `nlmsg_type` is one of `types`.
+    pub fn nlmsg_type(mut self, types: &[u16]) -> Self {
+        self.stmt(BPF_LD | BPF_H | BPF_ABS, NLMSG_TYPE_OFFSET);
+        for (i, &t) in types.iter().enumerate() {
+            let k = u16::from_be_bytes(t.to_ne_bytes()) as u32;
+            if i == types.len() - 1 {
+                self.expect(k);
+            } else {
+                let rest = types.len() - 1 - i;
+                self.jump(
+                    BPF_JMP | BPF_JEQ | BPF_K,
+                    k,
+                    Target::Skip(rest),
+                    Target::Next,
+                );
+            }
+        }
+        if types.is_empty() {
+            self.stmt(BPF_RET | BPF_K, 0);
+        }
+        self
+    }
+
+    fn header(mut self, size: u16, offset: usize, k: u32) -> Self {
+        self.stmt(BPF_LD | size | BPF_ABS, (Msghdr::HDRLEN + offset) as u32);
+        self.expect(k);
+        self
+    }
+
+    /// accepts messages whose family header has `v` at `offset`.
+    pub fn header_u8(self, offset: usize, v: u8) -> Self {
+        self.header(BPF_B, offset, v as u32)
+    }
+
+    pub fn header_u16(self, offset: usize, v: u16) -> Self {
+        self.header(BPF_H, offset, u16::from_be_bytes(v.to_ne_bytes()) as u32)
+    }
+
+    pub fn header_u32(self, offset: usize, v: u32) -> Self {
+        self.header(BPF_W, offset, u32::from_be_bytes(v.to_ne_bytes()))
+    }
+
+    // A = offset of the attribute, or reject if not found.
+    fn find_attr<T: Into<u16>>(&mut self, hdrlen: usize, atype: T) {
+        let start = Msghdr::HDRLEN + crate::align(hdrlen);
+        self.stmt(BPF_LDX | BPF_W | BPF_IMM, atype.into() as u32);
+        self.stmt(BPF_LD | BPF_W | BPF_IMM, start as u32);
+        self.stmt(BPF_LD | BPF_W | BPF_ABS, SKF_AD_NLATTR);
+        self.jump(BPF_JMP | BPF_JEQ | BPF_K, 0, Target::Reject, Target::Next);
+    }
+
+    /// accepts messages which have the top-level attribute `atype`, after
+    /// the family header of `hdrlen` bytes.
+    pub fn attr<T: Into<u16>>(mut self, hdrlen: usize, atype: T) -> Self {
+        self.find_attr(hdrlen, atype);
+        self
+    }
+
+    fn attr_value<T: Into<u16>>(mut self, hdrlen: usize, atype: T, size: u16, k: u32) -> Self {
+        self.find_attr(hdrlen, atype);
+        self.stmt(BPF_MISC | BPF_TAX, 0);
+        self.stmt(BPF_LD | size | BPF_IND, 4);
+        self.expect(k);
+        self
+    }
+
+    /// accepts messages which have the top-level attribute `atype` of `v`.
+    pub fn attr_u8<T: Into<u16>>(self, hdrlen: usize, atype: T, v: u8) -> Self {
+        self.attr_value(hdrlen, atype, BPF_B, v as u32)
+    }
+
+    pub fn attr_u16<T: Into<u16>>(self, hdrlen: usize, atype: T, v: u16) -> Self {
+        self.attr_value(
+            hdrlen,
+            atype,
+            BPF_H,
+            u16::from_be_bytes(v.to_ne_bytes()) as u32,
+        )
+    }
+
+    pub fn attr_u32<T: Into<u16>>(self, hdrlen: usize, atype: T, v: u32) -> Self {
+        self.attr_value(hdrlen, atype, BPF_W, u32::from_be_bytes(v.to_ne_bytes()))
+    }
+
+    /// returns the program to be passed to `Socket::attach_filter()`.
+    ///
+    /// `E2BIG` is returned if a jump is too far for classic BPF, e.g. by
+    /// too many types given to `nlmsg_type()`.
+    pub fn build(&self) -> Result<Vec<sock_filter>> {
+        // BPF loads in big endian, so that control messages are told by
+        // equality, not by NLMSG_MIN_TYPE.
+        // accepted by its own return, not to jump over the predicates.
+        let mut insns = vec![(
+            BPF_LD | BPF_H | BPF_ABS,
+            NLMSG_TYPE_OFFSET,
+            Target::Next,
+            Target::Next,
+        )];
+        let controls = [
+            libc::NLMSG_NOOP,
+            libc::NLMSG_ERROR,
+            libc::NLMSG_DONE,
+            libc::NLMSG_OVERRUN,
+        ];
+        for (i, &t) in controls.iter().enumerate() {
+            let k = u16::from_be_bytes((t as u16).to_ne_bytes()) as u32;
+            let rest = controls.len() - 1 - i;
+            let jf = if rest == 0 {
+                Target::Skip(1)
+            } else {
+                Target::Next
+            };
+            insns.push((BPF_JMP | BPF_JEQ | BPF_K, k, Target::Skip(rest), jf));
+        }
+        insns.push((BPF_RET | BPF_K, u32::MAX, Target::Next, Target::Next));
+        insns.extend_from_slice(&self.insns);
+        let accept = insns.len();
+        insns.push((BPF_RET | BPF_K, u32::MAX, Target::Next, Target::Next));
+        insns.push((BPF_RET | BPF_K, 0, Target::Next, Target::Next));
+
+        let len = insns.len();
+        let offset = |i: usize, target: Target| -> Result<u8> {
+            let to = match target {
+                Target::Next => return Ok(0),
+                Target::Skip(n) => i + 1 + n,
+                Target::Accept => accept,
+                Target::Reject => accept + 1,
+            };
+            if to >= len {
+                return Err(Errno(libc::EINVAL).into());
+            }
+            u8::try_from(to - i - 1).map_err(|_| Errno(libc::E2BIG).into())
+        };
+        insns
+            .iter()
+            .enumerate()
+            .map(|(i, &(code, k, jt, jf))| {
+                Ok(sock_filter {
+                    code,
+                    jt: offset(i, jt)?,
+                    jf: offset(i, jf)?,
+                    k,
+                })
+            })
+            .collect()
+    }
+}
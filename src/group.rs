@@ -0,0 +1,170 @@
+use std::mem;
+
+use errno::Errno;
+use libc::{self, genlmsghdr};
+use {Attr, CbStatus, MsgVec, Result, Socket};
+
+/// A multicast group, by id or by the name of a generic netlink family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group<'a> {
+    /// the id as is, e.g. `libc::RTNLGRP_LINK`.
+    Id(u32),
+    /// resolved through the generic netlink controller.
+    Genl { family: &'a str, name: &'a str },
+}
+
+impl From<u32> for Group<'static> {
+    fn from(id: u32) -> Self {
+        Group::Id(id)
+    }
+}
+
+/// `(family, name)` of a generic netlink group.
+impl<'a> From<(&'a str, &'a str)> for Group<'a> {
+    fn from((family, name): (&'a str, &'a str)) -> Self {
+        Group::Genl { family, name }
+    }
+}
+
+impl<'a> Group<'a> {
+    /// returns the id of the group
+    ///
+    /// A generic netlink group is looked up by `CTRL_CMD_GETFAMILY` on a
+    /// temporary socket. `ENOENT` is returned if the family has no such
+    /// group.
+    pub fn id(&self) -> Result<u32> {
+        match *self {
+            Group::Id(id) => Ok(id),
+            Group::Genl { family, name } => genl_group_id(family, name),
+        }
+    }
+}
+
+fn genl_group_id(family: &str, name: &str) -> Result<u32> {
+    let mut nl = Socket::open(libc::NETLINK_GENERIC, libc::SOCK_CLOEXEC as u32)?;
+    nl.bind(0, crate::SOCKET_AUTOPID)?;
+    let mut nlv = MsgVec::new();
+    nlv.try_put_header()?.nlmsg_type = libc::GENL_ID_CTRL as u16;
+    let genl = nlv.put_extra_header::<genlmsghdr>()?;
+    genl.cmd = libc::CTRL_CMD_GETFAMILY as u8;
+    genl.version = 1;
+    nlv.put_cstr(libc::CTRL_ATTR_FAMILY_NAME as u16, family)?;
+
+    let mut id = None;
+    nl.request(&mut nlv, |nlh| {
+        for attr in nlh.attrs(mem::size_of::<genlmsghdr>()) {
+            let attr = attr?;
+            if attr.atype() != libc::CTRL_ATTR_MCAST_GROUPS as u16 {
+                continue;
+            }
+            for grp in attr.nested() {
+                if let Some(gid) = mcast_group_id(grp?, name)? {
+                    id = Some(gid);
+                }
+            }
+        }
+        Ok(CbStatus::Ok)
+    })?;
+    id.ok_or_else(|| Errno(libc::ENOENT).into())
+}
+
+// returns the id of a CTRL_ATTR_MCAST_GROUPS entry if its name is `name`.
+fn mcast_group_id(grp: &Attr, name: &str) -> Result<Option<u32>> {
+    let mut gname = None;
+    let mut gid = None;
+    for attr in grp.nested() {
+        let attr = attr?;
+        match attr.atype() as i32 {
+            libc::CTRL_ATTR_MCAST_GRP_NAME => gname = Some(attr.cstr()?),
+            libc::CTRL_ATTR_MCAST_GRP_ID => gid = Some(attr.value::<u32>()?),
+            _ => {}
+        }
+    }
+    Ok(if gname == Some(name) { gid } else { None })
+}
+
+/// The multicast groups a socket has joined, from `NETLINK_LIST_MEMBERSHIPS`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupSet {
+    // bit n - 1 is for the group n
+    words: Vec<u32>,
+}
+
+impl GroupSet {
+    /// creates from the bitmap of `NETLINK_LIST_MEMBERSHIPS`.
+    pub fn from_words(words: Vec<u32>) -> Self {
+        Self { words }
+    }
+
+    pub fn as_words(&self) -> &[u32] {
+        &self.words
+    }
+
+    pub fn contains(&self, group: u32) -> bool {
+        if group == 0 {
+            return false;
+        }
+        let bit = (group - 1) as usize;
+        matches!(self.words.get(bit / 32), Some(w) if w & (1 << (bit % 32)) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// returns the groups in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.words.len() * 32)
+            .filter(move |bit| self.words[bit / 32] & (1 << (bit % 32)) != 0)
+            .map(|bit| bit as u32 + 1)
+    }
+}
+
+/// Leaves the group on drop, returned by `Socket::join_guard()`.
+pub struct MembershipGuard<'a> {
+    nl: &'a Socket,
+    group: u32,
+}
+
+impl<'a> MembershipGuard<'a> {
+    pub fn group(&self) -> u32 {
+        self.group
+    }
+}
+
+impl<'a> Drop for MembershipGuard<'a> {
+    fn drop(&mut self) {
+        let _ = self.nl.drop_membership(self.group);
+    }
+}
+
+impl Socket {
+    /// returns the groups joined, by bind or by `join()`.
+    pub fn memberships(&self) -> Result<GroupSet> {
+        Ok(GroupSet::from_words(self.list_membership()?))
+    }
+
+    /// joins the multicast group, and returns its id.
+    pub fn join<'a, G: Into<Group<'a>>>(&self, group: G) -> Result<u32> {
+        let id = group.into().id()?;
+        self.add_membership(id)?;
+        Ok(id)
+    }
+
+    /// leaves the multicast group, and returns its id.
+    pub fn leave<'a, G: Into<Group<'a>>>(&self, group: G) -> Result<u32> {
+        let id = group.into().id()?;
+        self.drop_membership(id)?;
+        Ok(id)
+    }
+
+    /// joins the multicast group until the guard returned is dropped.
+    pub fn join_guard<'a, G: Into<Group<'a>>>(&self, group: G) -> Result<MembershipGuard<'_>> {
+        let group = self.join(group)?;
+        Ok(MembershipGuard { nl: self, group })
+    }
+}
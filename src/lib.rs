@@ -12,25 +12,93 @@
 #![allow(dead_code)]
 
 extern crate errno;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
 extern crate libc;
-use errno::Errno;
+#[cfg(feature = "mio")]
+extern crate mio;
+#[cfg(feature = "derive")]
+extern crate rsmnl_derive;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+pub use errno::Errno;
 
+#[cfg(feature = "tokio")]
+mod async_socket;
 mod attr;
+mod batch;
+mod builder;
 mod callback;
+mod dispatch;
+mod error;
+mod ext_ack;
+mod filter;
+mod group;
+mod listener;
+mod mmsg;
 mod msgvec;
+mod netns;
+mod nfnl;
 mod nlmsg;
+mod policy;
+mod recvbuf;
 mod socket;
 
+#[cfg(feature = "tokio")]
+pub use async_socket::AsyncSocket;
+#[cfg(feature = "tokio")]
+pub use async_socket::EventStream;
 pub use attr::Attr;
 pub use attr::AttrTbl;
-pub use attr::NestAttr;
+pub use attr::Attrs;
+pub use batch::BatchWriter;
+pub use batch::Chunk;
+pub use batch::ChunkAcks;
+pub use builder::SockOpt;
+pub use builder::SocketBuilder;
 pub use callback::run as cb_run;
 pub use callback::run2 as cb_run2;
 pub use callback::NOCB;
+pub use dispatch::Dispatcher;
+pub use error::Error;
+pub use ext_ack::ExtAck;
+pub use ext_ack::ExtAckPolicy;
+pub use ext_ack::KernelError;
+pub use filter::Filter;
+pub use group::Group;
+pub use group::GroupSet;
+pub use group::MembershipGuard;
+pub use listener::Event;
+pub use listener::EventListener;
+pub use mmsg::RecvBatch;
 pub use msgvec::MsgVec;
+pub use msgvec::NestGuard;
+pub use netns::NetnsRef;
+pub use nfnl::NfnlBatch;
+pub use nlmsg::Messages;
 pub use nlmsg::Msghdr;
+pub use nlmsg::OwnedMsg;
+pub use policy::AttrPolicy;
+pub use policy::Policy;
+pub use policy::PolicyError;
+pub use policy::PolicyViolation;
+pub use recvbuf::RecvBuf;
+pub use recvbuf::RecvStorage;
+pub use socket::Dump;
+pub use socket::DumpRetry;
+pub use socket::RecvMeta;
 pub use socket::Socket;
 
+#[cfg(feature = "derive")]
+pub use rsmnl_derive::AttrTbl;
+
+// used by the code rsmnl-derive generates.
+#[doc(hidden)]
+pub mod __private {
+    pub use errno::Errno;
+    pub use libc::EOPNOTSUPP;
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum AttrDataType {
     UNSPEC,
@@ -57,15 +125,8 @@ pub enum CbStatus {
 pub const ALIGNTO: usize = 4;
 pub const SOCKET_AUTOPID: u32 = 0;
 
-pub type Result<T> = std::result::Result<T, Errno>;
-pub type GenError = Box<dyn std::error::Error>;
-#[macro_export]
-macro_rules! gen_errno {
-    ($e: expr) => {
-        Err(GenError::from(Errno($e)))
-    };
-}
-pub type CbResult = std::result::Result<CbStatus, GenError>;
+pub type Result<T> = std::result::Result<T, Error>;
+pub type CbResult = Result<CbStatus>;
 
 #[inline]
 pub fn align(len: usize) -> usize {
@@ -82,29 +143,27 @@ pub fn socket_buffer_size() -> usize {
     }
 }
 
-pub fn default_buffer() -> Vec<u8> {
-    vec![0u8; socket_buffer_size()]
+/// returns a heap buffer of `socket_buffer_size()` bytes.
+pub fn default_buffer() -> RecvBuf {
+    RecvBuf::new(socket_buffer_size())
 }
 
 pub const SOCKET_DUMP_SIZE: usize = 32768;
 
-pub fn dump_buffer() -> [u8; SOCKET_DUMP_SIZE] {
-    [0u8; SOCKET_DUMP_SIZE]
+/// returns a stack buffer of `SOCKET_DUMP_SIZE` bytes.
+pub fn dump_buffer() -> RecvBuf<[u32; SOCKET_DUMP_SIZE / 4]> {
+    RecvBuf::stack()
 }
 
 /// @imitates: [mnl_attr_parse_payload]
 pub fn parse_payload<T: FnMut(&Attr) -> CbResult>(payload: &[u8], mut cb: T) -> CbResult {
-    let mut ret: CbResult = gen_errno!(libc::ENOENT);
-    let mut attr: &Attr = unsafe { &*(payload.as_ptr() as *const _ as *const Attr) };
-    while attr.ok(
-        payload.as_ptr() as *const _ as isize + payload.len() as isize - attr as *const _ as isize,
-    ) {
-        ret = cb(attr);
+    let mut ret: CbResult = Err(Errno(libc::ENOENT).into());
+    for attr in Attrs::new(payload) {
+        ret = cb(attr?);
         match ret {
             Ok(CbStatus::Ok) => {}
             _ => return ret,
         }
-        unsafe { attr = attr.next() };
     }
     ret
 }
@@ -0,0 +1,235 @@
+use std::{mem, slice};
+
+use errno::Errno;
+use libc::{self, c_int, nlmsgerr};
+use {Error, KernelError, MsgVec, Msghdr, OwnedMsg, RecvBuf, Result, Socket};
+
+/// An event returned by `EventListener::next_event()`.
+#[derive(Debug)]
+pub enum Event {
+    /// a notification, e.g. multicast message.
+    Msg(OwnedMsg),
+    /// notifications were lost by `ENOBUFS` or `NLMSG_OVERRUN`, so that the
+    /// state built from them is no longer reliable. If a resync request is
+    /// set, its dump follows as `Resync` and `Synced`.
+    Desync,
+    /// a message of the resync dump.
+    Resync(OwnedMsg),
+    /// the resync dump has completed.
+    Synced,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Synced,
+    // no resync request to recover by
+    Desynced,
+    // the resync request is not sent yet
+    Pending,
+    // dumping by the seq, which is stale and to be redone if desynced
+    Dumping(u32, bool),
+}
+
+/// Receives notifications from a `Socket`, detecting overrun.
+///
+/// When notifications are lost, `Event::Desync` is returned instead of
+/// `ENOBUFS`. With a resync request, e.g. `RTM_GETLINK`, the listener dumps by
+/// it on the same socket, so that the dumped messages are ordered after the
+/// notifications received before. The socket must be bound to tell the
+/// replies, and messages to the other requests are discarded, then it should
+/// be used for the notifications only.
+///
+/// ```no_run
+/// # extern crate libc;
+/// # extern crate rsmnl as mnl;
+/// # use mnl::{Event, EventListener, MsgVec, Socket};
+/// # fn main() -> mnl::Result<()> {
+/// let mut nl = Socket::open(libc::NETLINK_ROUTE, 0)?;
+/// nl.bind(libc::RTMGRP_LINK as u32, mnl::SOCKET_AUTOPID)?;
+/// let mut nlv = MsgVec::new();
+/// nlv.put_header().nlmsg_type = libc::RTM_GETLINK;
+/// nlv.put_extra_header::<[u8; 16]>()?;
+/// let mut listener = EventListener::new(nl).resync_with(nlv);
+/// // the initial state
+/// listener.resync()?;
+/// loop {
+///     match listener.next_event()? {
+///         Event::Desync => println!("clear the cache"),
+///         Event::Msg(msg) | Event::Resync(msg) => println!("{:?}", msg.msghdr()),
+///         Event::Synced => println!("the cache is in sync"),
+///     }
+/// }
+/// # }
+/// ```
+pub struct EventListener {
+    nl: Socket,
+    request: Option<MsgVec>,
+    state: State,
+    buf: RecvBuf,
+    len: usize,
+    offset: usize,
+}
+
+impl EventListener {
+    pub fn new(nl: Socket) -> Self {
+        Self {
+            nl,
+            request: None,
+            state: State::Synced,
+            buf: RecvBuf::new(crate::SOCKET_DUMP_SIZE),
+            len: 0,
+            offset: 0,
+        }
+    }
+
+    /// sets the request to dump the whole state on desync, which
+    /// `NLM_F_REQUEST | NLM_F_DUMP` and the sequence number are set to.
+    pub fn resync_with(mut self, nlv: MsgVec) -> Self {
+        self.request = Some(nlv);
+        self
+    }
+
+    pub fn get_ref(&self) -> &Socket {
+        &self.nl
+    }
+
+    pub fn get_mut(&mut self) -> &mut Socket {
+        &mut self.nl
+    }
+
+    pub fn into_inner(self) -> Socket {
+        self.nl
+    }
+
+    /// returns false from `Desync` until `Synced`.
+    ///
+    /// Without a resync request, this remains false after `Desync`.
+    pub fn is_synced(&self) -> bool {
+        self.state == State::Synced
+    }
+
+    /// dumps by the resync request on the next `next_event()`, e.g. to
+    /// build the initial state.
+    ///
+    /// `EINVAL` is returned if no request is set.
+    pub fn resync(&mut self) -> Result<()> {
+        if self.request.is_none() {
+            return Err(Errno(libc::EINVAL).into());
+        }
+        self.state = State::Pending;
+        Ok(())
+    }
+
+    // the messages left in the buffer are still handled, e.g. NLMSG_DONE of
+    // the dump.
+    fn desync(&mut self) -> Event {
+        self.state = match self.state {
+            // a socket can not dump twice at the same time
+            State::Dumping(seq, _) => State::Dumping(seq, true),
+            _ if self.request.is_some() => State::Pending,
+            _ => State::Desynced,
+        };
+        Event::Desync
+    }
+
+    /// returns the next event
+    ///
+    /// This blocks on a blocking socket. On a non-blocking one, `EAGAIN` is
+    /// returned as `Socket::recvfrom()`, and the call can be repeated.
+    pub fn next_event(&mut self) -> Result<Event> {
+        loop {
+            if self.state == State::Pending {
+                let nlv = self.request.as_mut().unwrap();
+                let seq = self.nl.prepare(nlv, libc::NLM_F_DUMP as u16)?;
+                self.nl.sendto(nlv)?;
+                self.state = State::Dumping(seq, false);
+            }
+            if self.offset >= self.len {
+                match self.nl.recvfrom(&mut self.buf) {
+                    Ok(nrecv) => {
+                        self.len = nrecv;
+                        self.offset = 0;
+                    }
+                    Err(Error::Os(errno)) if errno.0 == libc::ENOBUFS => {
+                        // the rest is stale
+                        self.offset = self.len;
+                        return Ok(self.desync());
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            // not to borrow self, which offset is updated
+            let buf = unsafe { slice::from_raw_parts(self.buf.as_ptr(), self.len) };
+            let rest = &buf[self.offset..];
+            let nlh = match Msghdr::from_bytes(rest) {
+                Ok(nlh) => nlh,
+                Err(err) => {
+                    let offset = mem::replace(&mut self.offset, self.len);
+                    return Err(match err {
+                        Error::Malformed { .. } => Error::Malformed { offset },
+                        err => err,
+                    });
+                }
+            };
+            self.offset += crate::align(nlh.nlmsg_len as usize).min(rest.len());
+
+            if nlh.nlmsg_pid == self.nl.portid() && self.nl.portid() != 0 {
+                match self.state {
+                    State::Dumping(seq, stale) if nlh.nlmsg_seq == seq => {
+                        if let Some(event) = self.dumped(nlh, stale)? {
+                            return Ok(event);
+                        }
+                    }
+                    // replies to an abandoned dump
+                    _ => {}
+                }
+                continue;
+            }
+            match nlh.nlmsg_type as c_int {
+                libc::NLMSG_OVERRUN => return Ok(self.desync()),
+                t if t >= libc::NLMSG_MIN_TYPE => return Ok(Event::Msg(OwnedMsg::from(nlh))),
+                _ => {}
+            }
+        }
+    }
+
+    // handles a reply to the resync request.
+    fn dumped(&mut self, nlh: &Msghdr, stale: bool) -> Result<Option<Event>> {
+        let t = nlh.nlmsg_type as c_int;
+        if stale {
+            if t == libc::NLMSG_DONE || t == libc::NLMSG_ERROR {
+                self.state = State::Pending;
+            }
+            return Ok(None);
+        }
+        if nlh.nlmsg_flags & libc::NLM_F_DUMP_INTR as u16 != 0 {
+            // the rest of this dump is stale, then dump again after it
+            let event = self.desync();
+            if t == libc::NLMSG_DONE || t == libc::NLMSG_ERROR {
+                self.state = State::Pending;
+            }
+            return Ok(Some(event));
+        }
+        match t {
+            libc::NLMSG_DONE => {
+                self.state = State::Synced;
+                // may carry an error code as int
+                if let Ok(err) = nlh.payload::<c_int>() {
+                    if *err < 0 {
+                        return Err(Errno(-*err).into());
+                    }
+                }
+                Ok(Some(Event::Synced))
+            }
+            libc::NLMSG_ERROR => {
+                if nlh.payload::<nlmsgerr>()?.error != 0 {
+                    self.state = State::Synced;
+                    return Err(KernelError::from_nlmsg(nlh)?.into());
+                }
+                Ok(None)
+            }
+            t if t >= libc::NLMSG_MIN_TYPE => Ok(Some(Event::Resync(OwnedMsg::from(nlh)))),
+            _ => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{mem, os::unix::io::AsRawFd, ptr, slice};
+
+use libc::{self, c_int, c_void, sockaddr_nl};
+use {Error, MsgVec, Result, Socket};
+
+/// Buffers for `Socket::recv_batch()`, which receives up to `capacity()`
+/// datagrams by one `recvmmsg()`.
+///
+/// Each buffer is aligned to `ALIGNTO`, and reused by the next receive.
+pub struct RecvBatch {
+    buf: Vec<u32>,
+    // bytes of each buffer, aligned to ALIGNTO
+    size: usize,
+    lens: Vec<usize>,
+    flags: Vec<c_int>,
+    len: usize,
+    // recvmmsg() arguments pointing to buf, which is never reallocated
+    iovs: Vec<libc::iovec>,
+    hdrs: Vec<libc::mmsghdr>,
+}
+
+// the pointers in iovs and hdrs refer only to the heap of buf and iovs.
+unsafe impl Send for RecvBatch {}
+unsafe impl Sync for RecvBatch {}
+
+impl RecvBatch {
+    /// creates `count` buffers of `size` bytes each.
+    pub fn new(count: usize, size: usize) -> Self {
+        let size = crate::align(size);
+        let mut buf = vec![0u32; count * size / mem::size_of::<u32>()];
+        let base = buf.as_mut_ptr() as *mut u8;
+        let mut iovs = (0..count)
+            .map(|i| libc::iovec {
+                iov_base: unsafe { base.add(i * size) } as *mut c_void,
+                iov_len: size,
+            })
+            .collect::<Vec<_>>();
+        let hdrs = iovs
+            .iter_mut()
+            .map(|iov| {
+                let mut hdr: libc::mmsghdr = unsafe { mem::zeroed() };
+                hdr.msg_hdr.msg_iov = iov;
+                hdr.msg_hdr.msg_iovlen = 1;
+                hdr
+            })
+            .collect::<Vec<_>>();
+        Self {
+            buf,
+            size,
+            lens: vec![0; count],
+            flags: vec![0; count],
+            len: 0,
+            iovs,
+            hdrs,
+        }
+    }
+
+    /// returns the number of datagrams received.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// returns the number of buffers.
+    pub fn capacity(&self) -> usize {
+        self.lens.len()
+    }
+
+    /// returns the `i`th datagram received
+    ///
+    /// `Error::Truncated` is returned if it did not fit in the buffer.
+    pub fn get(&self, i: usize) -> Option<Result<&[u8]>> {
+        if i >= self.len {
+            return None;
+        }
+        if self.flags[i] & libc::MSG_TRUNC != 0 {
+            return Some(Err(Error::Truncated {
+                capacity: self.size,
+            }));
+        }
+        let start = i * self.size;
+        Some(Ok(&self.bytes()[start..start + self.lens[i]]))
+    }
+
+    /// returns `msg_flags` of the `i`th datagram, e.g. `MSG_TRUNC`.
+    pub fn flags(&self, i: usize) -> Option<c_int> {
+        if i < self.len {
+            Some(self.flags[i])
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Result<&[u8]>> {
+        (0..self.len).map(move |i| self.get(i).unwrap())
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(
+                self.buf.as_ptr() as *const u8,
+                self.buf.len() * mem::size_of::<u32>(),
+            )
+        }
+    }
+}
+
+impl Socket {
+    /// receive datagrams into `batch`
+    ///
+    /// This function waits for the first datagram, then receives as many as
+    /// available up to `batch.capacity()` without blocking, by `recvmmsg()`
+    /// with `MSG_WAITFORONE`. It returns the number of datagrams received,
+    /// which is also `batch.len()`.
+    pub fn recv_batch(&self, batch: &mut RecvBatch) -> Result<usize> {
+        for hdr in batch.hdrs.iter_mut() {
+            hdr.msg_len = 0;
+            hdr.msg_hdr.msg_flags = 0;
+        }
+        batch.len = 0;
+        let ret = crate::socket::cvt(unsafe {
+            libc::recvmmsg(
+                self.as_raw_fd(),
+                batch.hdrs.as_mut_ptr(),
+                batch.hdrs.len() as _,
+                libc::MSG_WAITFORONE as _,
+                ptr::null_mut(),
+            )
+        })? as usize;
+        for (i, hdr) in batch.hdrs[..ret].iter().enumerate() {
+            batch.lens[i] = hdr.msg_len as usize;
+            batch.flags[i] = hdr.msg_hdr.msg_flags;
+        }
+        batch.len = ret;
+        Ok(ret)
+    }
+
+    /// send each of `nlvs` as a datagram by one `sendmmsg()`
+    ///
+    /// This function returns the number of datagrams sent, which may be less
+    /// than `nlvs.len()`. The rest should be sent again.
+    pub fn send_batch(&self, nlvs: &[&MsgVec]) -> Result<usize> {
+        let mut snl: sockaddr_nl = unsafe { mem::zeroed() };
+        snl.nl_family = libc::AF_NETLINK as u16;
+        let mut iovs = nlvs
+            .iter()
+            .map(|nlv| {
+                let buf = nlv.finalize()?;
+                Ok(libc::iovec {
+                    iov_base: buf.as_ptr() as *mut c_void,
+                    iov_len: buf.len(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut hdrs = iovs
+            .iter_mut()
+            .map(|iov| {
+                let mut hdr: libc::mmsghdr = unsafe { mem::zeroed() };
+                hdr.msg_hdr.msg_name = &mut snl as *mut _ as *mut c_void;
+                hdr.msg_hdr.msg_namelen = mem::size_of::<sockaddr_nl>() as u32;
+                hdr.msg_hdr.msg_iov = iov;
+                hdr.msg_hdr.msg_iovlen = 1;
+                hdr
+            })
+            .collect::<Vec<_>>();
+        let ret = crate::socket::cvt(unsafe {
+            libc::sendmmsg(self.as_raw_fd(), hdrs.as_mut_ptr(), hdrs.len() as _, 0)
+        })?;
+        Ok(ret as usize)
+    }
+}
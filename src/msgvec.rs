@@ -1,19 +1,23 @@
 use std::{
     convert::{AsRef, Into},
     marker::PhantomData,
-    mem, slice,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr, slice,
 };
 
 use errno::Errno;
 use libc;
-use {Attr, Msghdr, Result};
+use {Attr, Messages, Msghdr, Result};
 
 pub struct MsgVec {
     buf: Vec<u8>,
     nlmsg_len: isize, // offset to current, last nlmsghdr.
+    // offset to nested attr.nla_len, equals to attr itself.
     // not a address, might be changed on resizing.
-    nest_nla: Vec<isize>, // offset to nested attr.nla_len
-                          // equals to attr itself.
+    nest_nla: Vec<isize>,
+    // put fails beyond this, if bounded
+    max: Option<usize>,
 }
 
 /// MUST sync to linux/netlink.h::struct nlmsghdr
@@ -55,6 +59,7 @@ impl MsgVec {
             buf: Vec::with_capacity(crate::socket_buffer_size()),
             nlmsg_len: -1,
             nest_nla: Vec::new(),
+            max: None,
         }
     }
 
@@ -63,6 +68,60 @@ impl MsgVec {
             buf: Vec::with_capacity(size),
             nlmsg_len: -1,
             nest_nla: Vec::new(),
+            max: None,
+        }
+    }
+
+    /// creates a `MsgVec` whose puts are bounded to `max` bytes
+    ///
+    /// A put which would exceed `max` fails with `ENOSPC` and leaves the
+    /// messages as they were, as the libmnl `_check` functions. `max` is
+    /// e.g. `socket_buffer_size()` or the batch limit.
+    ///
+    /// `put_header()` can not fail, and grows beyond `max` if no room, then
+    /// the following puts and `finalize()` return `ENOSPC`. Use
+    /// `try_put_header()` to be rejected on putting.
+    ///
+    /// ```
+    /// let mut nlv = rsmnl::MsgVec::bounded(32);
+    /// nlv.put_header();
+    /// assert!(nlv.put(1u16, &2u64).is_ok());
+    /// assert!(nlv.put(1u16, &2u8).is_err());
+    /// assert!(nlv.len() == 28);
+    /// assert!(nlv.nlmsg_len() == 28);
+    /// ```
+    pub fn bounded(max: usize) -> Self {
+        Self {
+            buf: Vec::with_capacity(max),
+            nlmsg_len: -1,
+            nest_nla: Vec::new(),
+            max: Some(max),
+        }
+    }
+
+    /// returns the maximum size given to `bounded()`.
+    pub fn max_size(&self) -> Option<usize> {
+        self.max
+    }
+
+    /// sets or removes the maximum size
+    ///
+    /// `ENOSPC` is returned if the current length already exceeds it.
+    pub fn set_max_size(&mut self, max: Option<usize>) -> Result<()> {
+        if let Some(max) = max {
+            if self.buf.len() > max {
+                return Err(Errno(libc::ENOSPC).into());
+            }
+        }
+        self.max = max;
+        Ok(())
+    }
+
+    // ENOSPC if the buffer can not grow by size.
+    fn check_room(&self, size: usize) -> Result<()> {
+        match self.max {
+            Some(max) if self.buf.len() + size > max => Err(Errno(libc::ENOSPC).into()),
+            _ => Ok(()),
         }
     }
 
@@ -86,6 +145,61 @@ impl MsgVec {
         // self.buf.iter_mut().map(|x| *x = 0).count();
         self.buf.clear();
         self.nlmsg_len = -1;
+        self.nest_nla.clear();
+    }
+
+    /// shortens to the first `len` bytes, which must be a message boundary
+    ///
+    /// This removes the messages after `len`, e.g. to abort the ones put
+    /// after `len()` was taken. `EINVAL` is returned if `len` is not a
+    /// boundary, or a nest open is in the messages left.
+    ///
+    /// ```
+    /// let mut nlv = rsmnl::MsgVec::new();
+    /// nlv.put_header().nlmsg_type = 0x10;
+    /// let len = nlv.len();
+    /// nlv.put_header().nlmsg_type = 0x11;
+    /// nlv.nest_start(1u16).unwrap();
+    /// nlv.truncate(len).unwrap();
+    /// assert!(nlv.len() == 16 && nlv.nest_depth() == 0);
+    /// assert!(nlv.header().unwrap().nlmsg_type == 0x10);
+    /// ```
+    pub fn truncate(&mut self, len: usize) -> Result<()> {
+        if len > self.buf.len() || self.nest_nla.iter().any(|&o| (o as usize) < len) {
+            return Err(Errno(libc::EINVAL).into());
+        }
+        let mut last = -1;
+        let mut msgs = Messages::new(&self.buf[..len]);
+        loop {
+            let offset = msgs.offset();
+            match msgs.next() {
+                Some(Ok(_)) => last = offset as isize,
+                Some(Err(_)) => return Err(Errno(libc::EINVAL).into()),
+                None => break,
+            }
+        }
+        self.buf.truncate(len);
+        self.nlmsg_len = last;
+        self.nest_nla.clear();
+        Ok(())
+    }
+
+    /// returns the messages to send
+    ///
+    /// `EINPROGRESS` is returned if a nest is not ended, which leaves
+    /// `nla_len` zero. `Socket` checks this on sending a `MsgVec`, except
+    /// `sendto()` which takes any bytes. `ENOSPC` is returned if a bounded
+    /// one exceeds its size by `put_header()`.
+    pub fn finalize(&self) -> Result<&[u8]> {
+        if !self.nest_nla.is_empty() {
+            return Err(Errno(libc::EINPROGRESS).into());
+        }
+        if let Some(max) = self.max {
+            if self.buf.len() > max {
+                return Err(Errno(libc::ENOSPC).into());
+            }
+        }
+        Ok(&self.buf)
     }
 
     /// creates, reserve and prepare room for Netlink header
@@ -110,6 +224,10 @@ impl MsgVec {
     /// assert!(nlb.len() == 16);
     /// assert!(nlb.nlmsg_len() == 16);
     /// ```
+    ///
+    /// This does not fail even if a bounded `MsgVec` has no room, then it
+    /// grows beyond the bound and the following puts and `finalize()` fail.
+    /// Use `try_put_header()` to check on putting.
     pub fn put_header(&mut self) -> &mut Header {
         let old_len = self.buf.len();
         let new_len = old_len + Msghdr::HDRLEN as usize;
@@ -128,10 +246,31 @@ impl MsgVec {
         ret
     }
 
+    /// creates Netlink header as `put_header()`, but `ENOSPC` is returned if
+    /// a bounded `MsgVec` has no room for it.
+    pub fn try_put_header(&mut self) -> Result<&mut Header<'_>> {
+        self.check_room(Msghdr::HDRLEN)?;
+        Ok(self.put_header())
+    }
+
+    /// append a copy of `nlh` as a new message
+    ///
+    /// `ENOSPC` is returned if a bounded `MsgVec` has no room for it.
+    pub fn put_msg(&mut self, nlh: &Msghdr) -> Result<&mut Self> {
+        let bytes = nlh.as_bytes();
+        self.check_room(crate::align(bytes.len()))?;
+        let old_len = self.buf.len();
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(old_len + crate::align(bytes.len()), 0);
+        self.nlmsg_len = old_len as isize;
+        Ok(self)
+    }
+
     fn extends<T>(&mut self, size: usize) -> Result<&mut T> {
         if self.nlmsg_len < 0 {
-            return Err(Errno(libc::EBADMSG));
+            return Err(Errno(libc::EBADMSG).into());
         }
+        self.check_room(crate::align(size))?;
 
         let old_len = self.buf.len();
         let new_len = old_len + crate::align(size);
@@ -202,14 +341,20 @@ impl MsgVec {
     /// assert!(nlb.len() == 24);
     /// assert!(nlb.nlmsg_len() == 24);
     /// ```
+    ///
+    /// `EOVERFLOW` is returned if the attribute is too large for `nla_len`,
+    /// and `ENOSPC` if a bounded `MsgVec` has no room for it.
     pub fn put<T: Sized + Into<u16>, U: Copy>(&mut self, atype: T, data: &U) -> Result<&mut Self> {
-        let attr_len = Attr::HDRLEN as u16 + mem::size_of::<U>() as u16;
+        let attr_len = attr_len(mem::size_of::<U>())?;
         let attr = self.extends::<Attr>(attr_len as usize)?;
         attr.nla_type = atype.into();
         attr.nla_len = attr_len;
 
-        let dst = unsafe { attr.payload_raw_mut::<U>() };
-        *dst = *data;
+        // payload is aligned only to ALIGNTO, u64 may not be aligned.
+        unsafe {
+            let dst = (attr as *mut Attr as *mut u8).add(Attr::HDRLEN);
+            ptr::write_unaligned(dst as *mut U, *data);
+        }
         Ok(self)
     }
 
@@ -219,7 +364,7 @@ impl MsgVec {
         data: &[u8],
         len: usize,
     ) -> Result<&mut Self> {
-        let attr_len = (Attr::HDRLEN + len) as u16;
+        let attr_len = attr_len(len)?;
         let attr = self.extends::<Attr>(attr_len as usize)?;
         attr.nla_type = atype.into();
         attr.nla_len = attr_len;
@@ -294,13 +439,20 @@ impl MsgVec {
     /// This function updates the attribute header that identifies the nest.
     /// `start` pointer to the attribute nest returned by nest_start()
     ///
+    /// `EOVERFLOW` is returned if the nest is too large for `nla_len`, which
+    /// is left open to be cancelled.
+    ///
     /// @imitates: [libmnl::mnl_attr_nest_end]
     pub fn nest_end(&mut self) -> Result<&mut Self> {
         let len = self.buf.len() as isize;
         let offset = self.nest_nla.pop().ok_or(Errno(libc::EINVAL))?;
         if offset + Attr::HDRLEN as isize > len {
             self.nest_nla.push(offset);
-            return Err(Errno(libc::EINVAL));
+            return Err(Errno(libc::EINVAL).into());
+        }
+        if len - offset > u16::MAX as isize {
+            self.nest_nla.push(offset);
+            return Err(Errno(libc::EOVERFLOW).into());
         }
         unsafe {
             let start = self.buf.as_mut_ptr().offset(offset) as *mut _ as *mut u16;
@@ -317,14 +469,14 @@ impl MsgVec {
     /// @imitates: [libmnl::mnl_attr_nest_cancel]
     pub fn nest_cancel(&mut self) -> Result<&mut Self> {
         if self.nlmsg_len < 0 {
-            return Err(Errno(libc::EBADMSG));
+            return Err(Errno(libc::EBADMSG).into());
         }
 
         let len = self.buf.len() as isize;
         let offset = self.nest_nla.pop().ok_or(Errno(libc::EINVAL))?;
         if offset > len {
             self.nest_nla.push(offset);
-            return Err(Errno(libc::EINVAL));
+            return Err(Errno(libc::EINVAL).into());
         }
 
         // self.buf[offset..len].iter_mut().map(|x| *x = 0).count();
@@ -340,9 +492,60 @@ impl MsgVec {
         self.nest_nla.len()
     }
 
+    /// start an attribute nest, which is ended by the guard returned
+    ///
+    /// `NestGuard::end()` ends the nest. It is cancelled if the guard is
+    /// dropped without, e.g. by `?` or panic.
+    ///
+    /// ```
+    /// let mut nlv = rsmnl::MsgVec::new();
+    /// nlv.put_header();
+    /// let mut nest = nlv.nest_guard(1u16).unwrap();
+    /// nest.put(2u16, &3u32).unwrap();
+    /// nest.end().unwrap();
+    /// assert!(nlv.len() == 28);
+    /// {
+    ///     let mut nest = nlv.nest_guard(1u16).unwrap();
+    ///     nest.put(2u16, &3u32).unwrap();
+    /// }
+    /// assert!(nlv.len() == 28);
+    /// assert!(nlv.nest_depth() == 0);
+    /// ```
+    pub fn nest_guard<T: Sized + Into<u16>>(&mut self, atype: T) -> Result<NestGuard<'_>> {
+        self.nest_start(atype)?;
+        let depth = self.nest_depth();
+        Ok(NestGuard { nlv: self, depth })
+    }
+
+    /// put an attribute nest, whose attributes are put by `f`
+    ///
+    /// The nest is cancelled if `f` returns `Err`, which is returned.
+    ///
+    /// ```
+    /// let mut nlv = rsmnl::MsgVec::new();
+    /// nlv.put_header();
+    /// nlv.nest(1u16, |nlv| {
+    ///     nlv.put(2u16, &3u32)?;
+    ///     nlv.nest(4u16, |nlv| nlv.put(5u16, &6u8).map(|_| ()))?;
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert!(nlv.len() == 40);
+    /// ```
+    pub fn nest<T, F>(&mut self, atype: T, f: F) -> Result<&mut Self>
+    where
+        T: Sized + Into<u16>,
+        F: FnOnce(&mut MsgVec) -> Result<()>,
+    {
+        let mut guard = self.nest_guard(atype)?;
+        f(&mut guard)?;
+        guard.end()?;
+        Ok(self)
+    }
+
     pub fn header(&self) -> Result<&Header> {
         if self.nlmsg_len < 0 {
-            Err(Errno(libc::EBADMSG))
+            Err(Errno(libc::EBADMSG).into())
         } else {
             Ok(
                 unsafe {
@@ -352,9 +555,30 @@ impl MsgVec {
         }
     }
 
+    /// returns the mutable header of the current, last message.
+    pub fn header_mut(&mut self) -> Result<&mut Header<'_>> {
+        if self.nlmsg_len < 0 {
+            Err(Errno(libc::EBADMSG).into())
+        } else {
+            Ok(unsafe {
+                &mut *(self.buf.as_mut_ptr().offset(self.nlmsg_len) as *mut _ as *mut Header)
+            })
+        }
+    }
+
+    /// returns the message whose sequence number is `seq`
+    ///
+    /// This helps to find the request the kernel reported an error for, see
+    /// `KernelError::attr_path()`.
+    pub fn find(&self, seq: u32) -> Option<&Msghdr<'_>> {
+        Messages::new(&self.buf)
+            .map_while(|nlh| nlh.ok())
+            .find(|nlh| nlh.nlmsg_seq == seq)
+    }
+
     pub fn msghdr(&self) -> Result<&Msghdr> {
         if self.nlmsg_len < 0 {
-            Err(Errno(libc::EBADMSG))
+            Err(Errno(libc::EBADMSG).into())
         } else {
             Ok(
                 unsafe {
@@ -365,6 +589,14 @@ impl MsgVec {
     }
 }
 
+// nla_len of the payload, EOVERFLOW if it does not fit in u16.
+fn attr_len(payload_len: usize) -> Result<u16> {
+    match Attr::HDRLEN.checked_add(payload_len) {
+        Some(len) if len <= u16::MAX as usize => Ok(len as u16),
+        _ => Err(Errno(libc::EOVERFLOW).into()),
+    }
+}
+
 impl<'a> Header<'a> {
     /// might be for only test
     pub fn nlmsg_len(&self) -> u32 {
@@ -377,3 +609,58 @@ impl<'a> AsRef<[u8]> for Header<'a> {
         unsafe { slice::from_raw_parts(self as *const _ as *const u8, self._nlmsg_len as usize) }
     }
 }
+
+/// An attribute nest started by `MsgVec::nest_guard()`, which derefs to the
+/// `MsgVec` to put attributes in it.
+///
+/// The nest is cancelled on drop unless `end()` is called.
+pub struct NestGuard<'a> {
+    nlv: &'a mut MsgVec,
+    // nest_depth() of this nest
+    depth: usize,
+}
+
+impl<'a> NestGuard<'a> {
+    /// end the nest
+    ///
+    /// `EINVAL` is returned if a nest inside is not ended, and `EOVERFLOW` if
+    /// the nest is too large, then the nest is cancelled.
+    pub fn end(self) -> Result<()> {
+        if self.nlv.nest_depth() != self.depth {
+            return Err(Errno(libc::EINVAL).into());
+        }
+        self.nlv.nest_end()?;
+        mem::forget(self);
+        Ok(())
+    }
+
+    /// cancel the nest, removing the attributes put in it.
+    pub fn cancel(self) -> Result<()> {
+        // by drop
+        Ok(())
+    }
+}
+
+impl<'a> Drop for NestGuard<'a> {
+    fn drop(&mut self) {
+        while self.nlv.nest_depth() >= self.depth {
+            if self.nlv.nest_cancel().is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl<'a> Deref for NestGuard<'a> {
+    type Target = MsgVec;
+
+    fn deref(&self) -> &MsgVec {
+        self.nlv
+    }
+}
+
+impl<'a> DerefMut for NestGuard<'a> {
+    fn deref_mut(&mut self) -> &mut MsgVec {
+        self.nlv
+    }
+}
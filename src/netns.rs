@@ -0,0 +1,145 @@
+use std::{
+    fs::File,
+    os::unix::io::{AsRawFd, RawFd},
+    path::{Path, PathBuf},
+};
+
+use errno::Errno;
+use libc::{self, c_int, pid_t};
+use {CbStatus, Error, MsgVec, Result, Socket};
+
+// enum rtnl_netnsid_attr in linux/net_namespace.h
+const NETNSA_NSID: u16 = 1;
+const NETNSA_PID: u16 = 2;
+const NETNSA_FD: u16 = 3;
+const NETNSA_NSID_NOT_ASSIGNED: i32 = -1;
+
+/// A network namespace, specified by one of a path, a pid or an fd.
+#[derive(Debug, Clone, Copy)]
+pub enum NetnsRef<'a> {
+    /// a bind mounted namespace e.g. /var/run/netns/NAME, or
+    /// /proc/PID/ns/net.
+    Path(&'a Path),
+    /// the namespace the process belongs to.
+    Pid(pid_t),
+    /// an open namespace fd, which is not closed by this crate.
+    Fd(RawFd),
+}
+
+// the namespace resolved to an fd, or a pid as is.
+enum Resolved {
+    Pid(pid_t),
+    File(File),
+    Fd(RawFd),
+}
+
+impl<'a> NetnsRef<'a> {
+    // opens the namespace file for a pid too, if open_pid is set.
+    fn resolve(&self, open_pid: bool) -> Result<Resolved> {
+        match *self {
+            NetnsRef::Path(path) => Ok(Resolved::File(File::open(path)?)),
+            NetnsRef::Pid(pid) if open_pid => {
+                let path = PathBuf::from(format!("/proc/{}/ns/net", pid));
+                Ok(Resolved::File(File::open(path)?))
+            }
+            NetnsRef::Pid(pid) => Ok(Resolved::Pid(pid)),
+            NetnsRef::Fd(fd) => Ok(Resolved::Fd(fd)),
+        }
+    }
+}
+
+impl Resolved {
+    fn fd(&self) -> Option<RawFd> {
+        match *self {
+            Resolved::Pid(_) => None,
+            Resolved::File(ref f) => Some(f.as_raw_fd()),
+            Resolved::Fd(fd) => Some(fd),
+        }
+    }
+
+    fn put(&self, nlv: &mut MsgVec) -> Result<()> {
+        match *self {
+            Resolved::Pid(pid) => nlv.put(NETNSA_PID, &(pid as u32))?,
+            Resolved::File(ref f) => nlv.put(NETNSA_FD, &(f.as_raw_fd() as u32))?,
+            Resolved::Fd(fd) => nlv.put(NETNSA_FD, &(fd as u32))?,
+        };
+        Ok(())
+    }
+
+    // RTM_GETNSID or RTM_NEWNSID without NETNSA_NSID
+    fn nsid_msg(&self, mtype: u16) -> Result<MsgVec> {
+        let mut nlv = MsgVec::new();
+        nlv.try_put_header()?.nlmsg_type = mtype;
+        *nlv.put_extra_header::<u8>()? = libc::AF_UNSPEC as u8;
+        self.put(&mut nlv)?;
+        Ok(nlv)
+    }
+}
+
+fn setns(fd: RawFd) -> Result<()> {
+    ::socket::cvt(unsafe { libc::setns(fd, libc::CLONE_NEWNET) })?;
+    Ok(())
+}
+
+impl Socket {
+    /// open a netlink socket in the network namespace `ns`
+    ///
+    /// The calling thread enters `ns` by `setns(2)`, opens the socket and
+    /// then returns to the original namespace. A socket stays in the
+    /// namespace it was created in. This requires `CAP_SYS_ADMIN`.
+    ///
+    /// If the thread fails to return, `ECANCELED` is returned and the thread
+    /// remains in `ns`, so that it should not be used any more.
+    pub fn open_in_netns<T: Into<c_int>>(bus: T, flags: u32, ns: NetnsRef) -> Result<Self> {
+        let orig = File::open("/proc/thread-self/ns/net")?;
+        let target = ns.resolve(true)?;
+        setns(target.fd().unwrap())?;
+        let ret = Self::open(bus, flags);
+        if setns(orig.as_raw_fd()).is_err() {
+            return Err(Errno(libc::ECANCELED).into());
+        }
+        ret
+    }
+
+    fn nsid_request(&mut self, nlv: &mut MsgVec) -> Result<Option<i32>> {
+        let mut nsid = None;
+        self.request(nlv, |nlh| {
+            for attr in nlh.attrs(1) {
+                let attr = attr?;
+                if attr.atype() == NETNSA_NSID {
+                    let id = attr.value::<i32>()?;
+                    if id != NETNSA_NSID_NOT_ASSIGNED {
+                        nsid = Some(id);
+                    }
+                }
+            }
+            Ok(CbStatus::Ok)
+        })?;
+        Ok(nsid)
+    }
+
+    /// returns the id of `ns` seen from the namespace of this socket, or
+    /// `None` if it has not been assigned.
+    ///
+    /// This sends `RTM_GETNSID`, the socket must be `NETLINK_ROUTE`.
+    pub fn get_nsid(&mut self, ns: NetnsRef) -> Result<Option<i32>> {
+        let mut nlv = ns.resolve(false)?.nsid_msg(libc::RTM_GETNSID)?;
+        self.nsid_request(&mut nlv)
+    }
+
+    /// assigns an id to `ns` in the namespace of this socket by
+    /// `RTM_NEWNSID`, and returns the id assigned
+    ///
+    /// The kernel chooses the id if `nsid` is `None`. `EEXIST` is returned if
+    /// `ns` already has an id. This requires `CAP_NET_ADMIN`.
+    pub fn new_nsid(&mut self, ns: NetnsRef, nsid: Option<i32>) -> Result<i32> {
+        let target = ns.resolve(false)?;
+        let mut nlv = target.nsid_msg(libc::RTM_NEWNSID)?;
+        nlv.put(NETNSA_NSID, &nsid.unwrap_or(NETNSA_NSID_NOT_ASSIGNED))?;
+        self.nsid_request(&mut nlv)?;
+
+        let mut nlv = target.nsid_msg(libc::RTM_GETNSID)?;
+        self.nsid_request(&mut nlv)?
+            .ok_or_else(|| Error::from(Errno(libc::ENOENT)))
+    }
+}
@@ -0,0 +1,281 @@
+use std::{mem, ops::Deref};
+
+use errno::Errno;
+use libc::{self, c_int, nlmsgerr};
+use msgvec::Header;
+use {Error, KernelError, Messages, MsgVec, Msghdr, RecvBuf, Result, Socket};
+
+// linux/netfilter/nfnetlink.h::struct nfgenmsg
+#[repr(C)]
+struct Nfgenmsg {
+    nfgen_family: u8,
+    version: u8,
+    res_id: u16, // big endian
+}
+
+/// A transaction of an nfnetlink subsystem, e.g. nftables, which is framed by
+/// `NFNL_MSG_BATCH_BEGIN` and `NFNL_MSG_BATCH_END`.
+///
+/// The batch assigns the sequence numbers in order, from the begin message to
+/// the end message, and puts `NLM_F_REQUEST | NLM_F_ACK` to the messages
+/// between, so that each of them is reported after commit. The end message has
+/// `NLM_F_ACK` too, whose ACK completes the commit. The batch derefs to the
+/// `MsgVec` to read only, and puts to the current message by its own methods,
+/// not to put messages out of the transaction. They return `EINVAL` if no
+/// message has been put by `put_header()`, or after `end()`.
+///
+/// ```no_run
+/// # extern crate libc;
+/// # extern crate rsmnl as mnl;
+/// # use mnl::{MsgVec, NfnlBatch, Socket};
+/// # fn main() -> mnl::Result<()> {
+/// let mut nl = Socket::open(libc::NETLINK_NETFILTER, 0)?;
+/// nl.bind(0, mnl::SOCKET_AUTOPID)?;
+/// let seq = nl.next_seq();
+/// let mut batch = NfnlBatch::begin(MsgVec::new(), libc::NFNL_SUBSYS_NFTABLES as u16, seq)?;
+/// let nlh = batch.put_header()?;
+/// nlh.nlmsg_type = (libc::NFNL_SUBSYS_NFTABLES << 8) as u16; // | NFT_MSG_NEWTABLE
+/// batch.put_extra_header::<[u8; 4]>()?; // nfgenmsg
+/// // ... put attributes
+/// batch.end()?;
+/// for (nlh, err) in batch.commit(&nl)? {
+///     println!("message with seq {} has failed: {}", nlh.nlmsg_seq, err);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct NfnlBatch {
+    nlv: MsgVec,
+    res_id: u16,
+    // the sequence number of the begin message
+    begin_seq: u32,
+    // the next sequence number
+    seq: u32,
+    // the sequence number of the end message, once ended
+    end_seq: Option<u32>,
+}
+
+impl NfnlBatch {
+    /// resets `nlv` and puts the begin message of `seq` for the subsystem
+    /// `res_id`, e.g. `NFNL_SUBSYS_NFTABLES`.
+    ///
+    /// `ENOSPC` is returned if a bounded `nlv` has no room.
+    pub fn begin(mut nlv: MsgVec, res_id: u16, seq: u32) -> Result<Self> {
+        nlv.reset();
+        put_frame(&mut nlv, libc::NFNL_MSG_BATCH_BEGIN, res_id, seq)?;
+        Ok(Self {
+            nlv,
+            res_id,
+            begin_seq: seq,
+            seq: seq.wrapping_add(1),
+            end_seq: None,
+        })
+    }
+
+    pub fn res_id(&self) -> u16 {
+        self.res_id
+    }
+
+    /// returns the sequence number the next message will have.
+    pub fn seq(&self) -> u32 {
+        self.seq
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.end_seq.is_some()
+    }
+
+    /// creates Netlink header of the next message in the transaction
+    ///
+    /// The header has the next sequence number and
+    /// `NLM_F_REQUEST | NLM_F_ACK`, which may be or-ed with the others, e.g.
+    /// `NLM_F_CREATE`. `ENOSPC` is returned if a bounded `MsgVec` has no room.
+    pub fn put_header(&mut self) -> Result<&mut Header<'_>> {
+        let nlh = self.nlv.try_put_header()?;
+        nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+        nlh.nlmsg_seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        Ok(nlh)
+    }
+
+    /// puts the end message, after which the transaction can be committed.
+    ///
+    /// `EALREADY` is returned if it has been ended, and `EINPROGRESS` if a nest
+    /// is open.
+    pub fn end(&mut self) -> Result<()> {
+        if self.end_seq.is_some() {
+            return Err(Errno(libc::EALREADY).into());
+        }
+        self.nlv.finalize()?;
+        put_frame(
+            &mut self.nlv,
+            libc::NFNL_MSG_BATCH_END,
+            self.res_id,
+            self.seq,
+        )?;
+        self.end_seq = Some(self.seq);
+        self.seq = self.seq.wrapping_add(1);
+        Ok(())
+    }
+
+    /// removes the messages put after the begin message, including the end
+    /// message, so that the transaction can be put again.
+    ///
+    /// The sequence numbers are not reused.
+    pub fn abort(&mut self) -> Result<()> {
+        // the begin message is the first and has no nest
+        let len = crate::align(Msghdr::HDRLEN + mem::size_of::<Nfgenmsg>());
+        self.nlv.truncate(len)?;
+        self.end_seq = None;
+        Ok(())
+    }
+
+    // returns the MsgVec to put to the current message, which is neither the
+    // begin nor the end message.
+    fn current(&mut self) -> Result<&mut MsgVec> {
+        if self.end_seq.is_some() || self.seq == self.begin_seq.wrapping_add(1) {
+            return Err(Errno(libc::EINVAL).into());
+        }
+        Ok(&mut self.nlv)
+    }
+
+    /// puts an extra header to the current message, e.g. `nfgenmsg`.
+    pub fn put_extra_header<T>(&mut self) -> Result<&mut T> {
+        self.current()?.put_extra_header()
+    }
+
+    pub fn put<T: Sized + Into<u16>, U: Copy>(&mut self, atype: T, data: &U) -> Result<&mut Self> {
+        self.current()?.put(atype, data)?;
+        Ok(self)
+    }
+
+    pub fn put_bytes<T: Sized + Into<u16>>(&mut self, atype: T, data: &[u8]) -> Result<&mut Self> {
+        self.current()?.put_bytes(atype, data)?;
+        Ok(self)
+    }
+
+    pub fn put_str<T: Sized + Into<u16>>(&mut self, atype: T, data: &str) -> Result<&mut Self> {
+        self.current()?.put_str(atype, data)?;
+        Ok(self)
+    }
+
+    pub fn put_cstr<T: Sized + Into<u16>>(&mut self, atype: T, data: &str) -> Result<&mut Self> {
+        self.current()?.put_cstr(atype, data)?;
+        Ok(self)
+    }
+
+    pub fn put_flag<T: Sized + Into<u16>>(&mut self, atype: T) -> Result<&mut Self> {
+        self.current()?.put_flag(atype)?;
+        Ok(self)
+    }
+
+    pub fn nest_start<T: Sized + Into<u16>>(&mut self, atype: T) -> Result<&mut Self> {
+        self.current()?.nest_start(atype)?;
+        Ok(self)
+    }
+
+    pub fn nest_end(&mut self) -> Result<&mut Self> {
+        self.current()?.nest_end()?;
+        Ok(self)
+    }
+
+    pub fn nest_cancel(&mut self) -> Result<&mut Self> {
+        self.current()?.nest_cancel()?;
+        Ok(self)
+    }
+
+    /// returns the mutable header of the current message, whose sequence
+    /// number should be kept to tell the reply.
+    pub fn header_mut(&mut self) -> Result<&mut Header<'_>> {
+        self.current()?.header_mut()
+    }
+
+    /// returns the message of the sequence number `seq`.
+    pub fn find(&self, seq: u32) -> Option<&Msghdr<'_>> {
+        self.nlv.find(seq)
+    }
+
+    pub fn into_inner(self) -> MsgVec {
+        self.nlv
+    }
+
+    /// sends the transaction and returns the messages failed with their
+    /// errors
+    ///
+    /// The subsystem replies while the transaction is sent, then the replies
+    /// are received without blocking, until the ACK of the end message which
+    /// comes last. The socket should not be used for the other requests
+    /// meanwhile. If a message fails, the subsystem usually aborts the whole
+    /// transaction and does not acknowledge the end message.
+    ///
+    /// `Err` is returned if the batch itself is rejected, e.g. for an unknown
+    /// `res_id`, `EINVAL` if it is not ended by `end()`, and `ENOMSG` if
+    /// neither the end message is acknowledged nor any message fails.
+    pub fn commit(&self, nl: &Socket) -> Result<Vec<(&Msghdr<'_>, Error)>> {
+        let end_seq = self.end_seq.ok_or(Errno(libc::EINVAL))?;
+        nl.sendto(&self.nlv)?;
+
+        let mut failed = Vec::new();
+        let mut rbuf = RecvBuf::new(crate::SOCKET_DUMP_SIZE);
+        loop {
+            let nrecv = match nl.recv_nowait(&mut rbuf) {
+                Ok(nrecv) => nrecv,
+                Err(Error::Os(errno)) if errno.0 == libc::EAGAIN => {
+                    if failed.is_empty() {
+                        return Err(Errno(libc::ENOMSG).into());
+                    }
+                    return Ok(failed);
+                }
+                Err(err) => return Err(err),
+            };
+            for nlh in Messages::new(&rbuf[..nrecv]) {
+                let nlh = nlh?;
+                nlh.portid_ok(nl.portid())?;
+                if nlh.nlmsg_type as c_int != libc::NLMSG_ERROR {
+                    continue;
+                }
+                let seq = nlh.nlmsg_seq;
+                let error = nlh.payload::<nlmsgerr>()?.error;
+                if seq == self.begin_seq || seq == end_seq {
+                    if error != 0 {
+                        return Err(KernelError::from_nlmsg(nlh)?.into());
+                    }
+                    if seq == end_seq {
+                        return Ok(failed);
+                    }
+                    continue;
+                }
+                if error != 0 {
+                    if let Some(req) = self.nlv.find(seq) {
+                        failed.push((req, KernelError::from_nlmsg(nlh)?.into()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// puts NFNL_MSG_BATCH_BEGIN or NFNL_MSG_BATCH_END.
+fn put_frame(nlv: &mut MsgVec, mtype: c_int, res_id: u16, seq: u32) -> Result<()> {
+    let nlh = nlv.try_put_header()?;
+    nlh.nlmsg_type = mtype as u16;
+    nlh.nlmsg_flags = libc::NLM_F_REQUEST as u16;
+    if mtype == libc::NFNL_MSG_BATCH_END {
+        // to tell the end of the replies
+        nlh.nlmsg_flags |= libc::NLM_F_ACK as u16;
+    }
+    nlh.nlmsg_seq = seq;
+    let nfg = nlv.put_extra_header::<Nfgenmsg>()?;
+    nfg.nfgen_family = libc::AF_UNSPEC as u8;
+    nfg.version = libc::NFNETLINK_V0 as u8;
+    nfg.res_id = res_id.to_be();
+    Ok(())
+}
+
+impl Deref for NfnlBatch {
+    type Target = MsgVec;
+
+    fn deref(&self) -> &MsgVec {
+        &self.nlv
+    }
+}
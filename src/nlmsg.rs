@@ -1,6 +1,6 @@
 use std::{fmt, marker::PhantomData, mem, slice};
 
-use crate::{Attr, CbResult, CbStatus, GenError, Result};
+use crate::{Attr, Attrs, CbResult, CbStatus, Error, Policy, Result};
 use errno::Errno;
 use libc;
 
@@ -86,7 +86,9 @@ impl<'a> Msghdr<'a> {
     /// @imitates: [libmnl::mnl_nlmsg_get_payload]
     pub fn payload<T>(&self) -> Result<&'a T> {
         if crate::align(Self::size::<T>()) > self.nlmsg_len as usize {
-            Err(Errno(libc::ENODATA))
+            Err(Errno(libc::ENODATA).into())
+        } else if (self as *const _ as usize + Self::HDRLEN) & (mem::align_of::<T>() - 1) != 0 {
+            Err(Errno(libc::EINVAL).into())
         } else {
             Ok(unsafe { self.payload_raw::<T>() })
         }
@@ -120,6 +122,27 @@ impl<'a> Msghdr<'a> {
             && self.nlmsg_len as isize <= len
     }
 
+    /// get a netlink message from the head of a buffer
+    ///
+    /// This function returns a reference to the netlink message placed at the
+    /// beginning of `buf`, after checking that the buffer is aligned to
+    /// `ALIGNTO` and has enough room for the whole message, i.e. the message
+    /// is neither truncated nor malformed. `EINVAL` is returned if the buffer
+    /// is misaligned, `Error::Malformed` if the message does not fit in it.
+    pub fn from_bytes(buf: &'a [u8]) -> Result<&'a Self> {
+        if buf.as_ptr() as usize & (crate::ALIGNTO - 1) != 0 {
+            return Err(Errno(libc::EINVAL).into());
+        }
+        if buf.len() < Self::HDRLEN {
+            return Err(Error::Malformed { offset: 0 });
+        }
+        let len = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        if len < Self::HDRLEN || len > buf.len() {
+            return Err(Error::Malformed { offset: 0 });
+        }
+        Ok(unsafe { &*(buf.as_ptr() as *const Self) })
+    }
+
     /// get the next netlink message in a multipart message
     /// This function returns a pointer to the next netlink message that is part
     /// of a multi-part netlink message. Netlink can batch several messages into
@@ -159,7 +182,10 @@ impl<'a> Msghdr<'a> {
     /// @imitates: [libmnl::mnl_nlmsg_seq_ok]
     pub fn seq_ok(&self, seq: u32) -> Result<()> {
         if self.nlmsg_seq != 0 && seq != 0 && self.nlmsg_seq != seq {
-            return Err(Errno(libc::EPROTO));
+            return Err(Error::SeqMismatch {
+                expected: seq,
+                actual: self.nlmsg_seq,
+            });
         }
         Ok(())
     }
@@ -178,7 +204,10 @@ impl<'a> Msghdr<'a> {
     /// @imitates: [libmnl::mnl_nlmsg_portid_ok]
     pub fn portid_ok(&self, portid: u32) -> Result<()> {
         if self.nlmsg_pid != 0 && portid != 0 && self.nlmsg_pid != portid {
-            return Err(Errno(libc::ESRCH));
+            return Err(Error::PortidMismatch {
+                expected: portid,
+                actual: self.nlmsg_pid,
+            });
         }
         Ok(())
     }
@@ -195,21 +224,161 @@ impl<'a> Msghdr<'a> {
     ///
     /// @imitates: [libmnl::mnl_attr_parse]
     pub fn parse<T: FnMut(&'a Attr<'a>) -> CbResult>(&self, offset: usize, mut cb: T) -> CbResult {
-        let mut ret: CbResult = crate::gen_errno!(libc::ENOENT);
-        let mut attr = unsafe { self.payload_offset::<Attr>(offset) };
-        loop {
-            if !attr.ok(unsafe {
-                self.payload_tail::<u8>() as libc::intptr_t
-                    - attr as *const _ as *const u8 as libc::intptr_t
-            }) {
-                return ret;
-            }
-            ret = cb(attr);
+        let mut ret: CbResult = Err(Errno(libc::ENOENT).into());
+        for attr in self.attrs(offset) {
+            ret = cb(attr?);
             match ret {
                 Ok(CbStatus::Ok) => {}
                 _ => return ret,
             }
-            attr = unsafe { attr.next() };
+        }
+        ret
+    }
+
+    /// parse attributes with validation
+    ///
+    /// This function is like `parse()` but all attributes in the message,
+    /// including nested ones the policy refers, are validated by `policy`
+    /// before calling `cb`. If one of them does not conform, `cb` is never
+    /// called and the returned error is `PolicyError`, which tells the
+    /// attribute and the reason, with its offset from the head of the message.
+    ///
+    /// @imitates: [netlink::nlmsg_parse]
+    pub fn parse_with_policy<T: FnMut(&'a Attr<'a>) -> CbResult>(
+        &self,
+        offset: usize,
+        policy: &Policy,
+        cb: T,
+    ) -> CbResult {
+        if Self::HDRLEN + crate::align(offset) > self.nlmsg_len as usize {
+            return Err(Errno(libc::ENODATA).into());
+        }
+        policy.check(
+            self as *const _ as usize,
+            self.attrs(offset),
+            &mut Vec::new(),
+        )?;
+        self.parse(offset, cb)
+    }
+
+    /// returns an iterator over the attributes of the message
+    ///
+    /// The attributes start after the extra header, whose size is specified by
+    /// `offset` as `parse()`. If the message is too short to hold the extra
+    /// header, the iterator yields `ENODATA` error.
+    ///
+    /// @imitates: [libmnl::mnl_attr_for_each]
+    pub fn attrs(&self, offset: usize) -> Attrs<'a> {
+        let start = Self::HDRLEN + crate::align(offset);
+        let buf = self.as_bytes();
+        if start > buf.len() {
+            return Attrs::failed(Errno(libc::ENODATA).into());
+        }
+        Attrs::new(&buf[start..])
+    }
+
+    /// resolves an offset into the message to the attribute path
+    ///
+    /// Returns attributes from the outermost to the one which `attr_offset`,
+    /// the offset from the head of the message such as `ExtAck::offset`,
+    /// points to or points inside of. Attributes with `NLA_F_NESTED` are
+    /// descended into. `offset` is the size of the extra header as `parse()`.
+    /// `None` is returned if `attr_offset` is not in any attribute.
+    pub fn attr_path(&self, offset: usize, attr_offset: usize) -> Option<Vec<&'a Attr<'a>>> {
+        let target = self as *const _ as usize + attr_offset;
+        let mut path = Vec::new();
+        if attr_path_in(self.attrs(offset), target, &mut path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// returns the whole message, header and payload, as bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        unsafe { slice::from_raw_parts(self as *const _ as *const u8, self.nlmsg_len as usize) }
+    }
+}
+
+// target is an address, not an offset.
+fn attr_path_in<'a>(attrs: Attrs<'a>, target: usize, path: &mut Vec<&'a Attr<'a>>) -> bool {
+    for attr in attrs {
+        let attr = match attr {
+            Ok(attr) => attr,
+            Err(_) => return false,
+        };
+        let head = attr.as_intptr() as usize;
+        if target < head || target >= head + attr.nla_len as usize {
+            continue;
+        }
+        path.push(attr);
+        if target != head && attr.nla_type & libc::NLA_F_NESTED as u16 != 0 {
+            attr_path_in(attr.nested(), target, path);
+        }
+        return true;
+    }
+    false
+}
+
+/// A struct for `Msghdr` stream iterator.
+///
+/// Netlink can batch several messages into one buffer, this iterator walks
+/// over them by slicing the buffer, so that each message is bounds checked
+/// before it is handed out. A truncated or misaligned message is returned as
+/// `Err` and ends the iteration.
+///
+/// ```
+/// let mut nlv = rsmnl::MsgVec::new();
+/// nlv.put_header().nlmsg_type = 0x10;
+/// nlv.put_header().nlmsg_type = 0x11;
+/// let types = rsmnl::Messages::new(nlv.as_ref())
+///     .map(|nlh| nlh.map(|nlh| nlh.nlmsg_type))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(types, [0x10, 0x11]);
+/// ```
+/// @imitates: [libmnl::mnl_nlmsg_ok, libmnl::mnl_nlmsg_next]
+pub struct Messages<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Messages<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, offset: 0 }
+    }
+
+    /// returns the offset of the next message from the head of the buffer.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<&'a Msghdr<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.buf[self.offset..];
+        if rest.is_empty() {
+            return None;
+        }
+        match Msghdr::from_bytes(rest) {
+            Ok(nlh) => {
+                // the last message may not be padded up to ALIGNTO
+                let len = crate::align(nlh.nlmsg_len as usize);
+                self.offset += len.min(rest.len());
+                Some(Ok(nlh))
+            }
+            Err(err) => {
+                let err = match err {
+                    Error::Malformed { .. } => Error::Malformed {
+                        offset: self.offset,
+                    },
+                    err => err,
+                };
+                self.offset = self.buf.len();
+                Some(Err(err))
+            }
         }
     }
 }
@@ -444,3 +613,41 @@ impl<'a> fmt::Debug for Msghdr<'a> {
         Ok(())
     }
 }
+
+/// An owned copy of a netlink message.
+///
+/// The message is copied into a buffer aligned to `ALIGNTO`, so that it can
+/// be kept after the receive buffer is reused.
+#[derive(Clone)]
+pub struct OwnedMsg {
+    buf: Vec<u32>,
+}
+
+impl OwnedMsg {
+    /// returns the message.
+    pub fn msghdr(&self) -> &Msghdr<'_> {
+        unsafe { &*(self.buf.as_ptr() as *const Msghdr) }
+    }
+
+    /// returns the whole message, header and payload, as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.msghdr().as_bytes()
+    }
+}
+
+impl<'a> From<&Msghdr<'a>> for OwnedMsg {
+    fn from(nlh: &Msghdr<'a>) -> Self {
+        let src = nlh.as_bytes();
+        let mut buf = vec![0u32; crate::align(src.len()) / mem::size_of::<u32>()];
+        unsafe {
+            slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, src.len()).copy_from_slice(src);
+        }
+        Self { buf }
+    }
+}
+
+impl fmt::Debug for OwnedMsg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.msghdr(), f)
+    }
+}
@@ -0,0 +1,353 @@
+use std::{convert::TryInto, error::Error, fmt};
+
+use crate::{attr::data_type_len, Attr, AttrDataType, Attrs};
+use errno::Errno;
+use libc;
+
+/// validation rule of an attribute type
+///
+/// Basically the payload is checked by `dtype` the same as `Attr::validate()`
+/// and then optional constraints are applied. All of them can be built in
+/// const context so that a policy table can be a `static`:
+///
+/// ```
+/// use rsmnl::{AttrDataType, AttrPolicy, Policy};
+///
+/// static INFO: Policy = Policy::new(&[
+///     (1, AttrPolicy::new(AttrDataType::NulString).max_len(15)),
+/// ]);
+/// static LINK: Policy = Policy::new(&[
+///     (4, AttrPolicy::new(AttrDataType::U32).range(68, 65535)),
+///     (18, AttrPolicy::nested(&INFO)),
+/// ])
+/// .reject_unknown();
+/// ```
+/// @imitates: [netlink::struct nla_policy]
+#[derive(Debug, Clone, Copy)]
+pub struct AttrPolicy<'p> {
+    pub dtype: AttrDataType,
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    pub exact_len: Option<usize>,
+    pub range: Option<(u64, u64)>,
+    pub nested: Option<&'p Policy<'p>>,
+}
+
+impl<'p> AttrPolicy<'p> {
+    pub const fn new(dtype: AttrDataType) -> Self {
+        Self {
+            dtype,
+            min_len: None,
+            max_len: None,
+            exact_len: None,
+            range: None,
+            nested: None,
+        }
+    }
+
+    /// `AttrDataType::Nested` whose attributes are validated by `policy`.
+    pub const fn nested(policy: &'p Policy<'p>) -> Self {
+        Self {
+            nested: Some(policy),
+            ..Self::new(AttrDataType::Nested)
+        }
+    }
+
+    /// minimum length of the payload, or of the string for `String` and
+    /// `NulString` not including the terminating NUL.
+    pub const fn min_len(self, len: usize) -> Self {
+        Self {
+            min_len: Some(len),
+            ..self
+        }
+    }
+
+    /// maximum length of the payload, or of the string for `String` and
+    /// `NulString` not including the terminating NUL.
+    pub const fn max_len(self, len: usize) -> Self {
+        Self {
+            max_len: Some(len),
+            ..self
+        }
+    }
+
+    /// exact length of the payload.
+    pub const fn exact_len(self, len: usize) -> Self {
+        Self {
+            exact_len: Some(len),
+            ..self
+        }
+    }
+
+    /// inclusive range of the value, for integer types only.
+    pub const fn range(self, min: u64, max: u64) -> Self {
+        Self {
+            range: Some((min, max)),
+            ..self
+        }
+    }
+}
+
+/// attribute validation policy table
+///
+/// Attribute types which are not in the table are ignored unless
+/// `reject_unknown()` is specified.
+#[derive(Debug, Clone, Copy)]
+pub struct Policy<'p> {
+    attrs: &'p [(u16, AttrPolicy<'p>)],
+    reject_unknown: bool,
+}
+
+impl<'p> Policy<'p> {
+    pub const fn new(attrs: &'p [(u16, AttrPolicy<'p>)]) -> Self {
+        Self {
+            attrs,
+            reject_unknown: false,
+        }
+    }
+
+    /// makes an attribute whose type is not in the table an error.
+    pub const fn reject_unknown(self) -> Self {
+        Self {
+            reject_unknown: true,
+            ..self
+        }
+    }
+
+    /// returns the policy of the attribute type.
+    pub fn get(&self, atype: u16) -> Option<&AttrPolicy<'p>> {
+        self.attrs
+            .iter()
+            .find(|(t, _)| *t == atype)
+            .map(|(_, policy)| policy)
+    }
+
+    /// validates all attributes `attrs` yields, descending into nests.
+    ///
+    /// `base` is the address offsets in the error are calculated from.
+    pub(crate) fn check(
+        &self,
+        base: usize,
+        mut attrs: Attrs,
+        path: &mut Vec<u16>,
+    ) -> std::result::Result<(), PolicyError> {
+        loop {
+            let offset = attrs.offset();
+            let attr = match attrs.next() {
+                None => return Ok(()),
+                Some(Ok(attr)) => attr,
+                Some(Err(_)) => {
+                    // offset is relative to the iterator, attrs are in the
+                    // payload of the previous one or the base itself.
+                    return Err(PolicyError {
+                        path: path.clone(),
+                        offset: base_offset(base, &attrs) + offset,
+                        violation: PolicyViolation::Malformed,
+                    });
+                }
+            };
+            path.push(attr.atype());
+            let err = |violation| PolicyError {
+                path: path.clone(),
+                offset: attr.as_intptr() as usize - base,
+                violation,
+            };
+            let policy = match self.get(attr.atype()) {
+                Some(policy) => policy,
+                None if self.reject_unknown => return Err(err(PolicyViolation::UnknownType)),
+                None => {
+                    path.pop();
+                    continue;
+                }
+            };
+            if let Err(violation) = policy.check(attr) {
+                return Err(err(violation));
+            }
+            if let Some(nested) = policy.nested {
+                nested.check(base, attr.nested(), path)?;
+            }
+            path.pop();
+        }
+    }
+}
+
+// offset of the buffer attrs iterates over from the base.
+fn base_offset(base: usize, attrs: &Attrs) -> usize {
+    (attrs.as_ptr() as usize).saturating_sub(base)
+}
+
+impl<'p> AttrPolicy<'p> {
+    fn check(&self, attr: &Attr) -> std::result::Result<(), PolicyViolation> {
+        let payload = &attr.as_bytes()[Attr::HDRLEN..];
+        let mut len = payload.len();
+
+        match self.dtype {
+            AttrDataType::U8
+            | AttrDataType::U16
+            | AttrDataType::U32
+            | AttrDataType::U64
+            | AttrDataType::MSecs
+            | AttrDataType::Flag => {
+                let expected = data_type_len(self.dtype) as usize;
+                if len != expected {
+                    return Err(PolicyViolation::InvalidLength { expected, len });
+                }
+            }
+            AttrDataType::String => {
+                if len == 0 {
+                    return Err(PolicyViolation::EmptyString);
+                }
+                if payload[len - 1] == 0 {
+                    len -= 1;
+                }
+            }
+            AttrDataType::NulString => {
+                if len == 0 {
+                    return Err(PolicyViolation::EmptyString);
+                }
+                if payload[len - 1] != 0 {
+                    return Err(PolicyViolation::NotNulTerminated);
+                }
+                len -= 1;
+            }
+            AttrDataType::Nested if len != 0 && len < Attr::HDRLEN => {
+                return Err(PolicyViolation::TooShort {
+                    min: Attr::HDRLEN,
+                    len,
+                });
+            }
+            _ => {}
+        }
+
+        if let Some(expected) = self.exact_len {
+            if payload.len() != expected {
+                return Err(PolicyViolation::InvalidLength {
+                    expected,
+                    len: payload.len(),
+                });
+            }
+        }
+        if let Some(min) = self.min_len {
+            if len < min {
+                return Err(PolicyViolation::TooShort { min, len });
+            }
+        }
+        if let Some(max) = self.max_len {
+            if len > max {
+                return Err(PolicyViolation::TooLong { max, len });
+            }
+        }
+        if let Some((min, max)) = self.range {
+            let value = match self.dtype {
+                AttrDataType::U8 => payload[0] as u64,
+                AttrDataType::U16 => u16::from_ne_bytes(payload.try_into().unwrap()) as u64,
+                AttrDataType::U32 => u32::from_ne_bytes(payload.try_into().unwrap()) as u64,
+                AttrDataType::U64 | AttrDataType::MSecs => {
+                    u64::from_ne_bytes(payload.try_into().unwrap())
+                }
+                _ => return Ok(()),
+            };
+            if value < min || value > max {
+                return Err(PolicyViolation::OutOfRange { value, min, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the reason why an attribute does not conform to the policy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyViolation {
+    /// the attribute is truncated or misaligned.
+    Malformed,
+    /// the attribute type is not in the policy which rejects unknown.
+    UnknownType,
+    /// the payload length differs from the data type or `exact_len`.
+    InvalidLength {
+        expected: usize,
+        len: usize,
+    },
+    TooShort {
+        min: usize,
+        len: usize,
+    },
+    TooLong {
+        max: usize,
+        len: usize,
+    },
+    EmptyString,
+    NotNulTerminated,
+    OutOfRange {
+        value: u64,
+        min: u64,
+        max: u64,
+    },
+}
+
+impl PolicyViolation {
+    /// returns the errno the kernel would report for this violation.
+    pub fn errno(&self) -> Errno {
+        match self {
+            PolicyViolation::Malformed => Errno(libc::EBADMSG),
+            PolicyViolation::UnknownType => Errno(libc::EOPNOTSUPP),
+            PolicyViolation::NotNulTerminated => Errno(libc::EINVAL),
+            _ => Errno(libc::ERANGE),
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyViolation::Malformed => write!(f, "malformed attribute"),
+            PolicyViolation::UnknownType => write!(f, "unknown attribute type"),
+            PolicyViolation::InvalidLength { expected, len } => {
+                write!(f, "invalid length {}, expected {}", len, expected)
+            }
+            PolicyViolation::TooShort { min, len } => {
+                write!(f, "length {} is shorter than {}", len, min)
+            }
+            PolicyViolation::TooLong { max, len } => {
+                write!(f, "length {} is longer than {}", len, max)
+            }
+            PolicyViolation::EmptyString => write!(f, "empty string"),
+            PolicyViolation::NotNulTerminated => write!(f, "string is not NUL terminated"),
+            PolicyViolation::OutOfRange { value, min, max } => {
+                write!(f, "value {} is out of range [{}, {}]", value, min, max)
+            }
+        }
+    }
+}
+
+/// An error which tells the attribute failed in policy validation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyError {
+    /// attribute types from the outermost to the failed one. For a malformed
+    /// attribute whose type can not be read, only the enclosing nests.
+    pub path: Vec<u16>,
+    /// offset of the failed attribute from the head of the message, or of
+    /// the nest for `Attr::parse_nested_with_policy()`.
+    pub offset: usize,
+    pub violation: PolicyViolation,
+}
+
+impl PolicyError {
+    pub fn errno(&self) -> Errno {
+        self.violation.errno()
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "attribute ")?;
+        for (i, atype) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", atype)?;
+        }
+        write!(f, " at offset {}: {}", self.offset, self.violation)
+    }
+}
+
+impl Error for PolicyError {}
@@ -0,0 +1,92 @@
+use std::{
+    mem,
+    ops::{Deref, DerefMut},
+    slice,
+};
+
+/// storage of `RecvBuf`, which consists of `u32` for the alignment.
+pub trait RecvStorage: AsRef<[u32]> + AsMut<[u32]> {}
+
+impl RecvStorage for Vec<u32> {}
+impl<const N: usize> RecvStorage for [u32; N] {}
+
+/// A receive buffer aligned to `ALIGNTO`.
+///
+/// `Msghdr` and `Attr` are cast from the buffer, which requires the
+/// alignment. This derefs to `[u8]`, so that it can be passed to
+/// `Socket::recvfrom()` and `cb_run()` as is.
+///
+/// ```
+/// // 8192 bytes on heap
+/// let heap = rsmnl::RecvBuf::new(8192);
+/// assert!(heap.len() == 8192);
+/// // 1024 * 4 bytes on stack
+/// let stack = rsmnl::RecvBuf::<[u32; 1024]>::stack();
+/// assert!(stack.len() == 4096);
+/// assert!(stack.as_ptr() as usize % rsmnl::ALIGNTO == 0);
+/// ```
+pub struct RecvBuf<S: RecvStorage = Vec<u32>> {
+    words: S,
+}
+
+impl RecvBuf {
+    /// allocates `size` bytes on heap, rounded up to `ALIGNTO`.
+    pub fn new(size: usize) -> Self {
+        Self {
+            words: vec![0u32; crate::align(size) / mem::size_of::<u32>()],
+        }
+    }
+
+    /// grows to `size` bytes if it is smaller.
+    pub fn grow(&mut self, size: usize) {
+        let words = crate::align(size) / mem::size_of::<u32>();
+        if self.words.len() < words {
+            self.words.resize(words, 0);
+        }
+    }
+}
+
+impl<const N: usize> RecvBuf<[u32; N]> {
+    /// creates `N * 4` bytes on stack.
+    pub fn stack() -> Self {
+        Self { words: [0u32; N] }
+    }
+}
+
+impl<S: RecvStorage> RecvBuf<S> {
+    pub fn from_storage(words: S) -> Self {
+        Self { words }
+    }
+
+    pub fn into_storage(self) -> S {
+        self.words
+    }
+}
+
+impl<S: RecvStorage> Deref for RecvBuf<S> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        let words = self.words.as_ref();
+        unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, mem::size_of_val(words)) }
+    }
+}
+
+impl<S: RecvStorage> DerefMut for RecvBuf<S> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let words = self.words.as_mut();
+        unsafe { slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, mem::size_of_val(words)) }
+    }
+}
+
+impl<S: RecvStorage> AsRef<[u8]> for RecvBuf<S> {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<S: RecvStorage> AsMut<[u8]> for RecvBuf<S> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
@@ -2,12 +2,17 @@ use std::{
     convert::Into,
     mem,
     os::unix::io::{AsRawFd, FromRawFd, RawFd},
-    ptr,
+    ptr, slice, thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use errno::Errno;
-use libc::{c_int, c_uint, c_void, sockaddr, sockaddr_nl};
-use Result;
+use libc::{c_int, c_uint, c_void, nlmsgerr, sockaddr, sockaddr_nl};
+#[cfg(feature = "mio")]
+use mio::{event, unix::SourceFd, Interest, Registry, Token};
+#[cfg(feature = "mio")]
+use std::io;
+use {CbResult, CbStatus, Error, KernelError, MsgVec, Msghdr, OwnedMsg, RecvBuf, Result};
 
 pub trait IsMinusOne {
     fn is_minus_one(&self) -> bool;
@@ -26,17 +31,66 @@ impl_is_minus_one! { i8 i16 i32 i64 isize }
 pub fn cvt<T: IsMinusOne>(t: T) -> Result<T> {
     if t.is_minus_one() {
         // Err(io::Error::last_os_error())
-        Err(errno::errno())
+        Err(errno::errno().into())
     } else {
         Ok(t)
     }
 }
 
+/// The source and ancillary data of a received message, returned by
+/// `Socket::recv_with_meta()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecvMeta {
+    /// portid of the sender, 0 for the kernel.
+    pub portid: u32,
+    /// `nl_groups` of the source address.
+    pub groups: u32,
+    /// destination multicast group from `NETLINK_PKTINFO`, `Some(0)` for
+    /// unicast.
+    pub group: Option<u32>,
+    /// netns id of the peer from `NETLINK_LISTEN_ALL_NSID`, which is sent
+    /// only if the peer has an id.
+    pub nsid: Option<i32>,
+}
+
+impl RecvMeta {
+    fn parse_control(&mut self, msg: &libc::msghdr) {
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+            while !cmsg.is_null() {
+                let data = libc::CMSG_DATA(cmsg);
+                if (*cmsg).cmsg_level == libc::SOL_NETLINK {
+                    match (*cmsg).cmsg_type {
+                        // struct nl_pktinfo { __u32 group; }
+                        libc::NETLINK_PKTINFO => {
+                            self.group = Some(ptr::read_unaligned(data as *const u32))
+                        }
+                        libc::NETLINK_LISTEN_ALL_NSID => {
+                            self.nsid = Some(ptr::read_unaligned(data as *const i32))
+                        }
+                        _ => {}
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+            }
+        }
+    }
+}
+
 /// A Netlink socket helpers.
 /// @imitates [libmnl::struct mnl_socket]
 pub struct Socket {
     fd: c_int,
     addr: sockaddr_nl,
+    seq: u32,
+}
+
+// initial sequence number, as examples of libmnl do.
+fn initial_seq() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(1)
 }
 
 impl Socket {
@@ -58,6 +112,7 @@ impl Socket {
         Ok(Self {
             fd: fd,
             addr: unsafe { mem::zeroed() },
+            seq: initial_seq(),
         })
     }
 
@@ -99,16 +154,19 @@ impl Socket {
             )
         })?;
         if addr_len as usize != mem::size_of::<sockaddr_nl>() {
-            return Err(Errno(libc::EINVAL));
+            return Err(Errno(libc::EINVAL).into());
         }
         if self.addr.nl_family as i32 != libc::AF_NETLINK {
-            return Err(Errno(libc::EINVAL));
+            return Err(Errno(libc::EINVAL).into());
         }
         Ok(())
     }
 
     /// send a netlink message of a certain size
     ///
+    /// `data` is sent as is, pass `MsgVec::finalize()` to check that no nest
+    /// is left open.
+    ///
     /// @imitates: [libmnl::mnl_socket_sendto]
     pub fn sendto<T: AsRef<[u8]>>(&self, data: &T) -> Result<usize> {
         let mut snl: sockaddr_nl = unsafe { mem::zeroed() };
@@ -129,38 +187,445 @@ impl Socket {
 
     /// receive a netlink message
     ///
-    /// If errno is set to ENOSPC, it means that the buffer that you have passed
-    /// to store the netlink message is too small, so you have received a
-    /// truncated message. To avoid this, you have to allocate a buffer of
+    /// If `Error::Truncated` is returned, it means that the buffer that you
+    /// have passed to store the netlink message is too small, so you have
+    /// received a truncated message. To avoid this, you have to allocate a buffer of
     /// `default_bufsize` (which is 8KB, see linux/netlink.h for more
     /// information). Using this buffer size ensures that your buffer is big
-    /// enough to store the netlink message without truncating it.
+    /// enough to store the netlink message without truncating it, or use
+    /// `recv_grow()` for a message which may be larger.
     ///
     /// @imitates: [libmnl::mnl_socket_recvfrom]
     pub fn recvfrom(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recvmsg(buf, 0, None)
+    }
+
+    // receives without blocking, even on a blocking socket.
+    pub(crate) fn recv_nowait(&self, buf: &mut [u8]) -> Result<usize> {
+        self.recvmsg(buf, libc::MSG_DONTWAIT, None)
+    }
+
+    /// receive a netlink message with its source and ancillary data
+    ///
+    /// Same as `recvfrom()` but returns `RecvMeta` too, which tells the
+    /// sender, the multicast group with `set_pktinfo(true)` and the netns id
+    /// of the peer with `set_listen_all_nsid(true)`.
+    pub fn recv_with_meta(&self, buf: &mut [u8]) -> Result<(usize, RecvMeta)> {
+        let mut meta = RecvMeta::default();
+        let nrecv = self.recvmsg(buf, 0, Some(&mut meta))?;
+        Ok((nrecv, meta))
+    }
+
+    fn recvmsg(&self, buf: &mut [u8], flags: c_int, meta: Option<&mut RecvMeta>) -> Result<usize> {
+        if buf.as_ptr() as usize & (crate::ALIGNTO - 1) != 0 {
+            return Err(Errno(libc::EINVAL).into());
+        }
         let mut addr = unsafe { mem::zeroed::<sockaddr_nl>() };
         let mut iov = libc::iovec {
             iov_base: buf.as_mut_ptr() as *mut _ as *mut c_void,
             iov_len: buf.len(),
         };
-        let mut msg = libc::msghdr {
-            msg_name: &mut addr as *mut _ as *mut c_void,
-            msg_namelen: mem::size_of::<sockaddr_nl>() as u32,
-            msg_iov: &mut iov,
-            msg_iovlen: 1,
-            msg_control: ptr::null_mut(),
-            msg_controllen: 0,
-            msg_flags: 0,
-        };
-        let ret = cvt(unsafe { libc::recvmsg(self.fd, &mut msg, 0) })?;
+        // enough for both NETLINK_PKTINFO and NETLINK_LISTEN_ALL_NSID
+        let mut control = [0usize; 8];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut addr as *mut _ as *mut c_void;
+        msg.msg_namelen = mem::size_of::<sockaddr_nl>() as u32;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if meta.is_some() {
+            msg.msg_control = control.as_mut_ptr() as *mut c_void;
+            msg.msg_controllen = mem::size_of_val(&control) as _;
+        }
+        let ret = cvt(unsafe { libc::recvmsg(self.fd, &mut msg, flags) })?;
         if msg.msg_flags & libc::MSG_TRUNC != 0 {
-            return Err(Errno(libc::ENOSPC));
+            return Err(Error::Truncated {
+                capacity: buf.len(),
+            });
         }
         if msg.msg_namelen as usize != mem::size_of::<sockaddr_nl>() {
-            return Err(Errno(libc::EINVAL));
+            return Err(Errno(libc::EINVAL).into());
+        }
+        if let Some(meta) = meta {
+            meta.portid = addr.nl_pid;
+            meta.groups = addr.nl_groups;
+            meta.parse_control(&msg);
         }
         Ok(ret as usize)
     }
+
+    /// returns the size of the next datagram without receiving it
+    ///
+    /// This blocks until a datagram arrives on a blocking socket, by
+    /// `MSG_PEEK | MSG_TRUNC`.
+    pub fn peek_size(&self) -> Result<usize> {
+        let ret = cvt(unsafe {
+            libc::recv(
+                self.fd,
+                ptr::null_mut(),
+                0,
+                libc::MSG_PEEK | libc::MSG_TRUNC,
+            )
+        })?;
+        Ok(ret as usize)
+    }
+
+    /// receive a netlink message, growing `buf` to fit it
+    ///
+    /// The size of the message is peeked before receiving, so that it is not
+    /// truncated however large it is. `buf` is never shrunk, and can be reused
+    /// for the next receive. This returns the message received in `buf`.
+    pub fn recv_grow<'a>(&self, buf: &'a mut RecvBuf) -> Result<&'a [u8]> {
+        buf.grow(self.peek_size()?);
+        let nrecv = self.recvfrom(buf)?;
+        Ok(&buf[..nrecv])
+    }
+}
+
+impl Socket {
+    /// receive until the socket would block
+    ///
+    /// A non-blocking socket which is polled edge-triggered, e.g. by mio, has
+    /// to be drained on each readiness. `f` is called with the messages of
+    /// each receive, then this function returns the number of receives on
+    /// `EAGAIN`, or when `f` returns `CbStatus::Stop`. In the latter case the
+    /// rest remains in the socket.
+    pub fn drain<F: FnMut(&[u8]) -> CbResult>(&self, buf: &mut [u8], mut f: F) -> Result<usize> {
+        let mut nbatch = 0;
+        loop {
+            let nrecv = match self.recvfrom(buf) {
+                Ok(nrecv) => nrecv,
+                Err(Error::Os(errno)) if errno.0 == libc::EAGAIN => return Ok(nbatch),
+                Err(err) => return Err(err),
+            };
+            nbatch += 1;
+            if f(&buf[..nrecv])? == CbStatus::Stop {
+                return Ok(nbatch);
+            }
+        }
+    }
+
+    /// returns a new sequence number for a request.
+    ///
+    /// Zero is skipped since it is not tracked by `Msghdr::seq_ok()`.
+    pub fn next_seq(&mut self) -> u32 {
+        self.seq = self.seq.wrapping_add(1);
+        if self.seq == 0 {
+            self.seq = 1;
+        }
+        self.seq
+    }
+
+    // sets a new sequence number and flags to the last message in nlv.
+    pub(crate) fn prepare(&mut self, nlv: &mut MsgVec, flags: u16) -> Result<u32> {
+        nlv.finalize()?;
+        let seq = self.next_seq();
+        let nlh = nlv.header_mut()?;
+        nlh.nlmsg_seq = seq;
+        nlh.nlmsg_flags |= libc::NLM_F_REQUEST as u16 | flags;
+        Ok(seq)
+    }
+
+    /// send a request and wait for its ACK
+    ///
+    /// This function assigns a new sequence number and sets `NLM_F_REQUEST`
+    /// and `NLM_F_ACK` to the last message in `nlv`, sends it, then calls
+    /// `handler` for each reply, e.g. to `NLM_F_ECHO`, until the ACK arrives.
+    /// An error the kernel reports in the ACK is returned as `Error::Kernel`.
+    ///
+    /// If `handler` returns `CbStatus::Stop` or `Err`, the rest of the
+    /// replies are received without calling it until the ACK, so that the
+    /// socket can be used for the next request. The error of `handler` is
+    /// returned then, prior to the one in the ACK.
+    pub fn request<F: FnMut(&Msghdr) -> CbResult>(
+        &mut self,
+        nlv: &mut MsgVec,
+        handler: F,
+    ) -> Result<()> {
+        let seq = self.prepare(nlv, libc::NLM_F_ACK as u16)?;
+        self.sendto(nlv)?;
+        let mut handler = ReplyHandler::new(handler);
+        for msg in Dump::new(self, seq, true) {
+            match msg {
+                Ok(msg) => handler.call(msg.msghdr()),
+                Err(err) => return handler.finish().and(Err(err)),
+            }
+        }
+        handler.finish()
+    }
+
+    /// send a dump request and iterate over the replies
+    ///
+    /// This function assigns a new sequence number and sets `NLM_F_REQUEST`
+    /// and `NLM_F_DUMP` to the last message in `nlv`, and sends it. The
+    /// returned iterator yields a copy of each reply until `NLMSG_DONE`. An
+    /// error from the kernel, or `Error::DumpInterrupted` ends the iteration.
+    pub fn dump(&mut self, nlv: &mut MsgVec) -> Result<Dump<'_>> {
+        let seq = self.prepare(nlv, libc::NLM_F_DUMP as u16)?;
+        self.sendto(nlv)?;
+        Ok(Dump::new(self, seq, false))
+    }
+
+    /// send a dump request until a consistent dump is made
+    ///
+    /// Like `dump()`, but this function collects all the replies. If any of
+    /// them has `NLM_F_DUMP_INTR`, the results are discarded and `nlv` is sent
+    /// again with a new sequence number, after waiting for the backoff of
+    /// `retry`. `Error::DumpRetryExhausted` is returned if all the attempts
+    /// were interrupted.
+    pub fn dump_retry(&mut self, nlv: &mut MsgVec, retry: &DumpRetry) -> Result<Vec<OwnedMsg>> {
+        let mut backoff = retry.backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let mut dump = self.dump(nlv)?;
+            dump.replies.fail_on_intr = false;
+            let msgs = dump.by_ref().collect::<Result<Vec<_>>>()?;
+            if !dump.interrupted() {
+                return Ok(msgs);
+            }
+            if attempts >= retry.max_attempts {
+                return Err(Error::DumpRetryExhausted { attempts });
+            }
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(retry.max_backoff);
+        }
+    }
+}
+
+/// retry policy of `Socket::dump_retry()`.
+///
+/// The backoff is doubled on each retry, up to `max_backoff`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DumpRetry {
+    /// the number of dumps to try, including the first one.
+    pub max_attempts: u32,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl DumpRetry {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+}
+
+impl Default for DumpRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+// calls the handler of a request until it stops or fails, keeping the first
+// error, so that the rest of the replies are consumed.
+pub(crate) struct ReplyHandler<F> {
+    handler: F,
+    stopped: bool,
+    error: Option<Error>,
+}
+
+impl<F: FnMut(&Msghdr) -> CbResult> ReplyHandler<F> {
+    pub(crate) fn new(handler: F) -> Self {
+        Self {
+            handler,
+            stopped: false,
+            error: None,
+        }
+    }
+
+    pub(crate) fn call(&mut self, nlh: &Msghdr) {
+        if self.stopped {
+            return;
+        }
+        match (self.handler)(nlh) {
+            Ok(CbStatus::Ok) => {}
+            Ok(CbStatus::Stop) => self.stopped = true,
+            Err(err) => {
+                self.stopped = true;
+                self.error = Some(err);
+            }
+        }
+    }
+
+    // returns the error of the handler, if any.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
+
+// a reply to the request, or that the received messages are consumed.
+pub(crate) enum Reply {
+    Msg(OwnedMsg),
+    End,
+    Empty,
+}
+
+// the receive buffer and the state of replies to a request, shared by Dump and
+// the async request.
+pub(crate) struct Replies {
+    seq: u32,
+    // an ACK ends replies, not only NLMSG_DONE.
+    until_ack: bool,
+    // NLM_F_DUMP_INTR is an error, or is only recorded.
+    fail_on_intr: bool,
+    interrupted: bool,
+    buf: RecvBuf,
+    len: usize,
+    offset: usize,
+}
+
+impl Replies {
+    pub(crate) fn new(seq: u32, until_ack: bool) -> Self {
+        Self {
+            seq,
+            until_ack,
+            fail_on_intr: true,
+            interrupted: false,
+            buf: RecvBuf::new(crate::SOCKET_DUMP_SIZE),
+            len: 0,
+            offset: 0,
+        }
+    }
+
+    // returns the whole buffer to receive into, then call filled().
+    pub(crate) fn buf_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+
+    pub(crate) fn filled(&mut self, len: usize) {
+        self.len = len;
+        self.offset = 0;
+    }
+
+    pub(crate) fn next_reply(&mut self, portid: u32) -> Result<Reply> {
+        // not to borrow self, which offset is updated
+        let buf = unsafe { slice::from_raw_parts(self.buf.as_ptr(), self.len) };
+        while self.offset < self.len {
+            let rest = &buf[self.offset..];
+            let nlh = match Msghdr::from_bytes(rest) {
+                Ok(nlh) => nlh,
+                Err(err) => {
+                    // the rest can not be parsed.
+                    let offset = mem::replace(&mut self.offset, self.len);
+                    return Err(match err {
+                        Error::Malformed { .. } => Error::Malformed { offset },
+                        err => err,
+                    });
+                }
+            };
+            self.offset += crate::align(nlh.nlmsg_len as usize).min(rest.len());
+            nlh.portid_ok(portid)?;
+            nlh.seq_ok(self.seq)?;
+            if nlh.nlmsg_flags & libc::NLM_F_DUMP_INTR as u16 != 0 {
+                self.interrupted = true;
+                if self.fail_on_intr {
+                    return Err(Error::DumpInterrupted);
+                }
+            }
+            match nlh.nlmsg_type as c_int {
+                libc::NLMSG_DONE => {
+                    // may carry an error code as int
+                    if let Ok(err) = nlh.payload::<c_int>() {
+                        if *err < 0 {
+                            return Err(Errno(-*err).into());
+                        }
+                    }
+                    return Ok(Reply::End);
+                }
+                libc::NLMSG_ERROR => {
+                    if nlh.payload::<nlmsgerr>()?.error != 0 {
+                        return Err(KernelError::from_nlmsg(nlh)?.into());
+                    }
+                    if self.until_ack {
+                        return Ok(Reply::End);
+                    }
+                }
+                t if t >= libc::NLMSG_MIN_TYPE => return Ok(Reply::Msg(OwnedMsg::from(nlh))),
+                _ => {}
+            }
+        }
+        Ok(Reply::Empty)
+    }
+}
+
+/// An iterator over replies to a request, returned by `Socket::dump()`.
+///
+/// Messages are received into its own buffer which is aligned to `ALIGNTO`
+/// and `SOCKET_DUMP_SIZE` bytes long.
+pub struct Dump<'a> {
+    nl: &'a Socket,
+    replies: Replies,
+    done: bool,
+}
+
+impl<'a> Dump<'a> {
+    fn new(nl: &'a Socket, seq: u32, until_ack: bool) -> Self {
+        Self {
+            nl,
+            replies: Replies::new(seq, until_ack),
+            done: false,
+        }
+    }
+
+    /// returns the sequence number of the request.
+    pub fn seq(&self) -> u32 {
+        self.replies.seq
+    }
+
+    /// returns true if a reply so far has `NLM_F_DUMP_INTR`.
+    pub fn interrupted(&self) -> bool {
+        self.replies.interrupted
+    }
+
+    fn next_msg(&mut self) -> Result<Option<OwnedMsg>> {
+        loop {
+            match self.replies.next_reply(self.nl.portid())? {
+                Reply::Msg(msg) => return Ok(Some(msg)),
+                Reply::End => return Ok(None),
+                Reply::Empty => {
+                    let nrecv = self.nl.recvfrom(self.replies.buf_mut())?;
+                    self.replies.filled(nrecv);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Dump<'a> {
+    type Item = Result<OwnedMsg>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_msg() {
+            Ok(Some(msg)) => Some(Ok(msg)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
 }
 
 impl Drop for Socket {
@@ -172,6 +637,31 @@ impl Drop for Socket {
     }
 }
 
+#[cfg(feature = "mio")]
+impl event::Source for Socket {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.fd).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.fd).deregister(registry)
+    }
+}
+
 impl AsRawFd for Socket {
     /// @imitates: [libmnl::mnl_socket_get_fd]
     fn as_raw_fd(&self) -> RawFd {
@@ -193,6 +683,7 @@ impl FromRawFd for Socket {
         let mut nl = Self {
             fd: fd,
             addr: mem::zeroed(),
+            seq: initial_seq(),
         };
         if addr.nl_family as i32 == libc::AF_NETLINK {
             nl.addr = addr;
@@ -218,6 +709,11 @@ macro_rules! set_bool_opt {
     };
 }
 
+// not in libc for all the versions and targets
+const NETLINK_EXT_ACK: c_int = 11;
+const NETLINK_GET_STRICT_CHK: c_int = 12;
+const SO_RCVBUFFORCE: c_int = 33;
+
 impl Socket {
     /// set Netlink socket option
     ///
@@ -240,9 +736,18 @@ impl Socket {
     ///
     /// @imitates: [libmnl::mnl_socket_setsockopt]
     unsafe fn setsockopt<T>(&self, otype: i32, opt: &T) -> Result<()> {
+        self.setsockopt_level(libc::SOL_NETLINK, otype, opt)
+    }
+
+    pub(crate) unsafe fn setsockopt_level<T>(
+        &self,
+        level: c_int,
+        otype: i32,
+        opt: &T,
+    ) -> Result<()> {
         cvt(libc::setsockopt(
             self.fd,
-            libc::SOL_NETLINK,
+            level,
             otype,
             opt as *const _ as *const c_void,
             mem::size_of::<T>() as u32,
@@ -254,11 +759,15 @@ impl Socket {
     ///
     /// @imitates: [libmnl::mnl_socket_getsockopt]
     unsafe fn getsockopt<T>(&self, otype: i32) -> Result<T> {
+        self.getsockopt_level(libc::SOL_NETLINK, otype)
+    }
+
+    unsafe fn getsockopt_level<T>(&self, level: c_int, otype: i32) -> Result<T> {
         let mut opt = mem::zeroed::<T>();
         let mut optlen = mem::size_of::<T>() as u32;
         cvt(libc::getsockopt(
             self.fd,
-            libc::SOL_NETLINK,
+            level,
             otype,
             &mut opt as *mut _ as *mut c_void,
             &mut optlen as *mut u32,
@@ -275,6 +784,7 @@ impl Socket {
     // NETLINK_LIST_MEMBERSHIPS		9
     // NETLINK_CAP_ACK			10
     // NETLINK_EXT_ACK			11
+    // NETLINK_GET_STRICT_CHK		12
 
     //getsockopt
     // case NETLINK_PKTINFO:
@@ -372,7 +882,8 @@ impl Socket {
                 &mut size,
             )
         })?;
-        let mut v = vec![0u32; size as usize];
+        // size is in bytes
+        let mut v = vec![0u32; (size as usize).div_ceil(mem::size_of::<u32>())];
         cvt(unsafe {
             libc::getsockopt(
                 self.fd,
@@ -390,8 +901,21 @@ impl Socket {
     }
 
     pub fn ext_ack(&self) -> Result<bool> {
-        // get_bool_opt!(self, libc::NETLINK_EXT_ACK)
-        get_bool_opt!(self, 11)
+        get_bool_opt!(self, NETLINK_EXT_ACK)
+    }
+
+    pub fn strict_chk(&self) -> Result<bool> {
+        get_bool_opt!(self, NETLINK_GET_STRICT_CHK)
+    }
+
+    /// returns `SO_RCVBUF`, which is the double of the size set.
+    pub fn rcvbuf(&self) -> Result<usize> {
+        Ok(unsafe { self.getsockopt_level::<c_int>(libc::SOL_SOCKET, libc::SO_RCVBUF)? } as usize)
+    }
+
+    /// returns `SO_SNDBUF`, which is the double of the size set.
+    pub fn sndbuf(&self) -> Result<usize> {
+        Ok(unsafe { self.getsockopt_level::<c_int>(libc::SOL_SOCKET, libc::SO_SNDBUF)? } as usize)
     }
 
     //setsockopt
@@ -505,8 +1029,34 @@ impl Socket {
     }
 
     pub fn set_ext_ack(&self, v: bool) -> Result<()> {
-        // set_bool_opt!(&self, libc::NETLINK_EXT_ACK, v)
-        set_bool_opt!(&self, 11, v)
+        set_bool_opt!(&self, NETLINK_EXT_ACK, v)
+    }
+
+    /// makes the kernel check dump requests strictly, and filter the dump by
+    /// the header and attributes of the request.
+    pub fn set_strict_chk(&self, v: bool) -> Result<()> {
+        set_bool_opt!(&self, NETLINK_GET_STRICT_CHK, v)
+    }
+
+    /// sets `SO_RCVBUF`, which is capped by `net.core.rmem_max`.
+    pub fn set_rcvbuf(&self, size: usize) -> Result<()> {
+        unsafe { self.setsockopt_level(libc::SOL_SOCKET, libc::SO_RCVBUF, &(size as c_int)) }
+    }
+
+    /// sets `SO_RCVBUFFORCE`, which is not capped but requires
+    /// `CAP_NET_ADMIN`. Falls back to `SO_RCVBUF` on `EPERM`.
+    pub fn set_rcvbuf_force(&self, size: usize) -> Result<()> {
+        let ret =
+            unsafe { self.setsockopt_level(libc::SOL_SOCKET, SO_RCVBUFFORCE, &(size as c_int)) };
+        match ret {
+            Err(Error::Os(errno)) if errno.0 == libc::EPERM => self.set_rcvbuf(size),
+            ret => ret,
+        }
+    }
+
+    /// sets `SO_SNDBUF`, which is capped by `net.core.wmem_max`.
+    pub fn set_sndbuf(&self, size: usize) -> Result<()> {
+        unsafe { self.setsockopt_level(libc::SOL_SOCKET, libc::SO_SNDBUF, &(size as c_int)) }
     }
 
     pub fn set_nonblock(&mut self) -> Result<()> {
@@ -8,14 +8,34 @@ use std::{
 extern crate libc;
 use libc::genlmsghdr;
 
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "mio")]
+extern crate mio;
 extern crate rsmnl as mnl;
-use mnl::{Attr, MsgVec, Msghdr, Socket};
+#[cfg(feature = "tokio")]
+extern crate tokio;
+use mnl::{Attr, AttrTbl, MsgVec, Msghdr, Socket};
 
 fn buf_offset_as<T>(buf: &[u8], offset: isize) -> &T {
     assert!(buf.len() >= offset as usize + mem::size_of::<T>());
     unsafe { (buf.as_ptr().offset(offset) as *const T).as_ref().unwrap() }
 }
 
+fn kernel_error(err: mnl::Error) -> mnl::KernelError {
+    match err {
+        mnl::Error::Kernel(err) => *err,
+        err => panic!("not a kernel error: {}", err),
+    }
+}
+
+fn policy_error(err: mnl::Error) -> mnl::PolicyError {
+    match err {
+        mnl::Error::Policy(err) => err,
+        err => panic!("not a policy error: {}", err),
+    }
+}
+
 fn set_buf<T>(buf: &mut [u8], offset: isize, v: T) {
     assert!(buf.len() >= offset as usize + mem::size_of::<T>());
     unsafe {
@@ -88,6 +108,1017 @@ fn socket_get_portid() {
     assert!(nls.portid() > 0);
 }
 
+#[test]
+fn socket_next_seq() {
+    let mut nls = default_socket!();
+    let seq = nls.next_seq();
+    assert!(seq != 0);
+    assert!(nls.next_seq() == seq.wrapping_add(1) || seq == u32::MAX);
+}
+
+// struct ifinfomsg
+fn rtnl_getlink(nlv: &mut MsgVec, ifindex: i32) {
+    nlv.put_header().nlmsg_type = libc::RTM_GETLINK;
+    let ifi = nlv.put_extra_header::<[i32; 4]>().unwrap();
+    ifi[0] = libc::AF_UNSPEC;
+    ifi[1] = ifindex;
+}
+
+#[test]
+fn socket_dump() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 0);
+    let msgs = nls
+        .dump(&mut nlv)
+        .unwrap()
+        .collect::<mnl::Result<Vec<_>>>()
+        .unwrap();
+    let nlh = nlv.msghdr().unwrap();
+    assert!(nlh.nlmsg_flags & libc::NLM_F_DUMP as u16 == libc::NLM_F_DUMP as u16);
+    assert!(!msgs.is_empty());
+    for msg in &msgs {
+        assert!(msg.msghdr().nlmsg_type == libc::RTM_NEWLINK);
+        assert!(msg.msghdr().nlmsg_seq == nlh.nlmsg_seq);
+    }
+}
+
+#[test]
+fn socket_dump_retry() {
+    let retry = mnl::DumpRetry::new(3).backoff(std::time::Duration::from_millis(1));
+    assert!(retry.max_attempts == 3);
+    assert!(retry.max_backoff == mnl::DumpRetry::default().max_backoff);
+
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 0);
+    let msgs = nls.dump_retry(&mut nlv, &retry).unwrap();
+    assert!(!msgs.is_empty());
+    let seq = nlv.msghdr().unwrap().nlmsg_seq;
+    assert!(msgs.iter().all(|msg| msg.msghdr().nlmsg_seq == seq));
+
+    let err = mnl::Error::DumpRetryExhausted { attempts: 3 };
+    assert!(err.errno() == Some(mnl::Errno(libc::EINTR)));
+    assert!(err.to_string() == "dump was interrupted 3 times");
+}
+
+#[test]
+fn socket_request() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+
+    // loopback
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    let mut nreply = 0;
+    nls.request(&mut nlv, |nlh| {
+        assert!(nlh.nlmsg_type == libc::RTM_NEWLINK);
+        nreply += 1;
+        Ok(mnl::CbStatus::Ok)
+    })
+    .unwrap();
+    assert!(nreply == 1);
+    assert!(nlv.msghdr().unwrap().nlmsg_flags & libc::NLM_F_ACK as u16 != 0);
+
+    // the ACK is consumed after Stop, then the next request succeeds
+    let mut nreply = 0;
+    nls.request(&mut nlv, |_| {
+        nreply += 1;
+        Ok(mnl::CbStatus::Stop)
+    })
+    .unwrap();
+    assert!(nreply == 1);
+    nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)).unwrap();
+
+    // so is after the handler fails, whose error is returned
+    match nls.request(&mut nlv, |_| Err(mnl::Errno(libc::ENOTSUP).into())) {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::ENOTSUP))),
+        Ok(_) => panic!("must be ENOTSUP"),
+    }
+    nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)).unwrap();
+
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, i32::MAX);
+    match nls.request(&mut nlv, |_| panic!("must not be called")) {
+        Err(mnl::Error::Kernel(err)) => {
+            assert!(err.errno.0 == libc::ENODEV);
+            assert!(err.seq() == Some(nlv.msghdr().unwrap().nlmsg_seq));
+        }
+        ret => panic!("unexpected: {:?}", ret),
+    }
+}
+
+#[test]
+fn socket_dispatcher() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+
+    let mut nlv = MsgVec::new();
+    let mut seqs = Vec::new();
+    for ifindex in &[1, i32::MAX, 1] {
+        rtnl_getlink(&mut nlv, *ifindex);
+        let nlh = nlv.header_mut().unwrap();
+        nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+        nlh.nlmsg_seq = nls.next_seq();
+        seqs.push(nlh.nlmsg_seq);
+    }
+
+    let mut nreply = 0;
+    let mut dispatcher = mnl::Dispatcher::new(nls.portid());
+    dispatcher.expect_batch(&nlv);
+    dispatcher.register(seqs[0], |nlh| {
+        assert!(nlh.nlmsg_type == libc::RTM_NEWLINK);
+        nreply += 1;
+        Ok(mnl::CbStatus::Ok)
+    });
+    dispatcher.register(seqs[2], |_| Err(mnl::Error::callback("stop")));
+    assert!(dispatcher.pending() == 3);
+    nls.sendto(&nlv).unwrap();
+
+    let results = dispatcher.recv_all(&nls).unwrap();
+    assert!(dispatcher.is_done());
+    assert!(results.iter().map(|r| r.0).collect::<Vec<_>>() == seqs);
+    assert!(results[0].1.is_ok());
+    match results[1].1 {
+        Err(mnl::Error::Kernel(ref err)) => assert!(err.errno.0 == libc::ENODEV),
+        ref ret => panic!("unexpected: {:?}", ret),
+    }
+    match results[2].1 {
+        Err(mnl::Error::Callback(ref err)) => assert!(err.to_string() == "stop"),
+        ref ret => panic!("unexpected: {:?}", ret),
+    }
+    drop(dispatcher);
+    assert!(nreply == 1);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn async_socket() {
+    use futures_core::Stream;
+    use std::{future, pin::Pin};
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    let _guard = rt.enter();
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let mut nls = mnl::AsyncSocket::new(nls).unwrap();
+
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    let mut nreply = 0;
+    rt.block_on(nls.request(&mut nlv, |nlh| {
+        assert!(nlh.nlmsg_type == libc::RTM_NEWLINK);
+        nreply += 1;
+        Ok(mnl::CbStatus::Ok)
+    }))
+    .unwrap();
+    assert!(nreply == 1);
+
+    // the ACK is consumed after Stop
+    rt.block_on(nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Stop)))
+        .unwrap();
+    rt.block_on(nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)))
+        .unwrap();
+    let ret = rt.block_on(nls.request(&mut nlv, |_| Err(mnl::Errno(libc::ENOTSUP).into())));
+    assert!(ret.unwrap_err().errno() == Some(mnl::Errno(libc::ENOTSUP)));
+    rt.block_on(nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)))
+        .unwrap();
+
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, i32::MAX);
+    match rt.block_on(nls.request(&mut nlv, |_| panic!("must not be called"))) {
+        Err(mnl::Error::Kernel(err)) => assert!(err.errno.0 == libc::ENODEV),
+        ret => panic!("unexpected: {:?}", ret),
+    }
+
+    // the stream yields any message, not only multicast.
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 0);
+    nlv.header_mut().unwrap().nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+    assert!(rt.block_on(nls.send(&nlv)).unwrap() == nlv.len());
+    let mut events = nls.events();
+    let msg = rt
+        .block_on(future::poll_fn(|cx| Pin::new(&mut events).poll_next(cx)))
+        .unwrap()
+        .unwrap();
+    assert!(msg.msghdr().nlmsg_type == libc::RTM_NEWLINK);
+}
+
+#[cfg(feature = "mio")]
+#[test]
+fn socket_mio_drain() {
+    use mio::{Events, Interest, Poll, Token};
+
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    nls.set_nonblock().unwrap();
+    let mut poll = Poll::new().unwrap();
+    poll.registry()
+        .register(&mut nls, Token(1), Interest::READABLE)
+        .unwrap();
+
+    let mut buf = vec![0u32; mnl::SOCKET_DUMP_SIZE / 4];
+    let buf = unsafe { std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, buf.len() * 4) };
+    assert!(nls.drain(buf, |_| panic!("must not be called")).unwrap() == 0);
+
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 0);
+    let nlh = nlv.header_mut().unwrap();
+    nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+    nlh.nlmsg_seq = nls.next_seq();
+    let seq = nlh.nlmsg_seq;
+    nls.sendto(&nlv).unwrap();
+
+    let mut events = Events::with_capacity(4);
+    let mut done = false;
+    while !done {
+        poll.poll(&mut events, Some(std::time::Duration::from_secs(1)))
+            .unwrap();
+        assert!(events.iter().any(|event| event.token() == Token(1)));
+        let nbatch = nls
+            .drain(buf, |batch| {
+                let ret = mnl::cb_run(batch, seq, nls.portid(), mnl::NOCB)?;
+                done = ret == mnl::CbStatus::Stop;
+                Ok(ret)
+            })
+            .unwrap();
+        assert!(nbatch > 0);
+    }
+    poll.registry().deregister(&mut nls).unwrap();
+}
+
+#[test]
+fn socket_batch() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+
+    let mut dispatcher = mnl::Dispatcher::new(nls.portid());
+    let mut nlvs = Vec::new();
+    for ifindex in &[1, i32::MAX] {
+        let mut nlv = MsgVec::new();
+        rtnl_getlink(&mut nlv, *ifindex);
+        let nlh = nlv.header_mut().unwrap();
+        nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+        nlh.nlmsg_seq = nls.next_seq();
+        dispatcher.expect_batch(&nlv);
+        nlvs.push(nlv);
+    }
+    assert!(nls.send_batch(&nlvs.iter().collect::<Vec<_>>()).unwrap() == 2);
+
+    let mut batch = mnl::RecvBatch::new(8, mnl::socket_buffer_size());
+    assert!(batch.capacity() == 8 && batch.is_empty());
+    let mut results = Vec::new();
+    while !dispatcher.is_done() {
+        let n = nls.recv_batch(&mut batch).unwrap();
+        assert!(n > 0 && n == batch.len());
+        assert!(batch.get(n).is_none() && batch.flags(n - 1) == Some(0));
+        for buf in batch.iter() {
+            results.extend(dispatcher.run(buf.unwrap()).unwrap());
+        }
+    }
+    assert!(results.len() == 2);
+    assert!(results[0].1.is_ok());
+    assert!(results[1].1.as_ref().unwrap_err().errno() == Some(mnl::Errno(libc::ENODEV)));
+
+    // RTM_NEWLINK does not fit in 16 bytes
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    nlv.header_mut().unwrap().nlmsg_flags = libc::NLM_F_REQUEST as u16;
+    nls.send_batch(&[&nlv]).unwrap();
+    let mut batch = mnl::RecvBatch::new(1, 16);
+    assert!(nls.recv_batch(&mut batch).unwrap() == 1);
+    assert!(batch.flags(0).unwrap() & libc::MSG_TRUNC != 0);
+    match batch.get(0) {
+        Some(Err(mnl::Error::Truncated { capacity: 16 })) => {}
+        ret => panic!("unexpected: {:?}", ret),
+    }
+}
+
+#[test]
+fn socket_recv_grow() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    let nlh = nlv.header_mut().unwrap();
+    nlh.nlmsg_flags = libc::NLM_F_REQUEST as u16;
+    nlh.nlmsg_seq = nls.next_seq();
+    let seq = nlh.nlmsg_seq;
+    nls.sendto(&nlv).unwrap();
+
+    let size = nls.peek_size().unwrap();
+    assert!(size > Msghdr::HDRLEN);
+    // peeking does not consume
+    assert!(nls.peek_size().unwrap() == size);
+
+    let mut buf = mnl::RecvBuf::new(0);
+    let msg = nls.recv_grow(&mut buf).unwrap();
+    assert!(msg.len() == size);
+    let nlh = Msghdr::from_bytes(msg).unwrap();
+    assert!(nlh.nlmsg_type == libc::RTM_NEWLINK && nlh.nlmsg_seq == seq);
+    assert!(buf.len() == mnl::align(size));
+}
+
+#[test]
+fn socket_recv_with_meta() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let mut buf = mnl::default_buffer();
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    nlv.header_mut().unwrap().nlmsg_flags = libc::NLM_F_REQUEST as u16;
+
+    nls.sendto(&nlv).unwrap();
+    let (nrecv, meta) = nls.recv_with_meta(&mut buf).unwrap();
+    assert!(nrecv > Msghdr::HDRLEN);
+    assert!(meta.portid == 0 && meta.groups == 0);
+    assert!(meta.group.is_none() && meta.nsid.is_none());
+
+    // unicast from the kernel has group 0
+    nls.set_pktinfo(true).unwrap();
+    nls.sendto(&nlv).unwrap();
+    let (_, meta) = nls.recv_with_meta(&mut buf).unwrap();
+    assert!(meta.portid == 0);
+    assert!(meta.group == Some(0));
+}
+
+#[test]
+fn socket_netns() {
+    // a new netns, held by the file
+    let ns = std::thread::spawn(|| {
+        assert!(unsafe { libc::unshare(libc::CLONE_NEWNET) } == 0);
+        std::fs::File::open("/proc/thread-self/ns/net").unwrap()
+    })
+    .join()
+    .unwrap();
+    let nsref = mnl::NetnsRef::Fd(ns.as_raw_fd());
+
+    let orig = std::fs::read_link("/proc/thread-self/ns/net").unwrap();
+    let mut nls = Socket::open_in_netns(libc::NETLINK_ROUTE, 0, nsref).unwrap();
+    assert!(std::fs::read_link("/proc/thread-self/ns/net").unwrap() == orig);
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    // only lo exists in the new netns
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 0);
+    assert!(nls.dump(&mut nlv).unwrap().count() == 1);
+
+    let path = std::path::Path::new("/proc/self/ns/net");
+    let nls = Socket::open_in_netns(libc::NETLINK_ROUTE, 0, mnl::NetnsRef::Path(path)).unwrap();
+    drop(nls);
+
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    assert!(nls.get_nsid(nsref).unwrap().is_none());
+    let nsid = nls.new_nsid(nsref, None).unwrap();
+    assert!(nls.get_nsid(nsref).unwrap() == Some(nsid));
+    match nls.new_nsid(nsref, None) {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::EEXIST))),
+        Ok(_) => panic!("must be EEXIST"),
+    }
+}
+
+#[test]
+fn socket_builder() {
+    let (nls, rejected) = mnl::SocketBuilder::new(libc::NETLINK_ROUTE)
+        .cloexec()
+        .rcvbuf(1 << 16)
+        .sndbuf(1 << 16)
+        .strict_chk(true)
+        .ext_ack(true)
+        .cap_ack(true)
+        .pktinfo(true)
+        .membership(libc::RTNLGRP_LINK)
+        .membership(libc::RTNLGRP_IPV4_IFADDR)
+        // no such group
+        .membership(0xffff)
+        .bind(0, mnl::SOCKET_AUTOPID)
+        .build()
+        .unwrap();
+    assert!(rejected.len() == 1);
+    assert!(rejected[0].0 == mnl::SockOpt::Membership(0xffff));
+    assert!(nls.portid() != 0);
+    assert!(nls.rcvbuf().unwrap() >= 1 << 16);
+    assert!(nls.sndbuf().unwrap() >= 1 << 16);
+    assert!(nls.strict_chk().unwrap());
+    assert!(nls.ext_ack().unwrap());
+    assert!(nls.cap_ack().unwrap());
+    assert!(nls.pktinfo().unwrap());
+    assert!(!nls.no_enobufs().unwrap());
+    // not cleared by bind
+    let groups = nls.memberships().unwrap();
+    assert!(groups.contains(libc::RTNLGRP_LINK));
+    assert!(groups.contains(libc::RTNLGRP_IPV4_IFADDR));
+    let flags = unsafe { libc::fcntl(nls.as_raw_fd(), libc::F_GETFD) };
+    assert!(flags & libc::FD_CLOEXEC != 0);
+}
+
+// returns nlmsg_type received for RTM_GETLINK of lo with the filter.
+fn filtered_getlink(filter: mnl::Filter) -> Vec<u16> {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    nls.attach_filter(&filter.build().unwrap()).unwrap();
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    nlv.header_mut().unwrap().nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+    nls.sendto(&nlv).unwrap();
+
+    let mut buf = mnl::default_buffer();
+    let mut types = Vec::new();
+    while types.last() != Some(&(libc::NLMSG_ERROR as u16)) {
+        let nrecv = nls.recvfrom(&mut buf).unwrap();
+        for nlh in mnl::Messages::new(&buf[..nrecv]) {
+            types.push(nlh.unwrap().nlmsg_type);
+        }
+    }
+    types
+}
+
+#[test]
+fn socket_filter() {
+    let newlink = libc::RTM_NEWLINK;
+    let ack = libc::NLMSG_ERROR as u16;
+    assert!(filtered_getlink(mnl::Filter::new()) == [newlink, ack]);
+    // the ACK is not filtered
+    let f = mnl::Filter::new().nlmsg_type(&[libc::RTM_NEWADDR]);
+    assert!(filtered_getlink(f) == [ack]);
+    let f =
+        mnl::Filter::new().nlmsg_type(&[libc::RTM_DELLINK, libc::RTM_NEWLINK, libc::RTM_NEWADDR]);
+    assert!(filtered_getlink(f) == [newlink, ack]);
+
+    // struct ifinfomsg { ifi_family: u8, pad: u8, ifi_type: u16, ifi_index: i32, .. }
+    let f = mnl::Filter::new()
+        .header_u8(0, libc::AF_UNSPEC as u8)
+        .header_u16(2, libc::ARPHRD_LOOPBACK)
+        .header_u32(4, 1);
+    assert!(filtered_getlink(f) == [newlink, ack]);
+    let f = mnl::Filter::new().header_u32(4, 2);
+    assert!(filtered_getlink(f) == [ack]);
+
+    let f = mnl::Filter::new().attr(16, libc::IFLA_IFNAME);
+    assert!(filtered_getlink(f) == [newlink, ack]);
+    // IFLA_MAX + 1
+    let f = mnl::Filter::new().attr(16, 0x7fffu16);
+    assert!(filtered_getlink(f) == [ack]);
+    let f = mnl::Filter::new().attr_u8(16, libc::IFLA_OPERSTATE, 0xff);
+    assert!(filtered_getlink(f) == [ack]);
+    let f = mnl::Filter::new().attr_u32(16, libc::IFLA_TXQLEN, 1000);
+    assert!(filtered_getlink(f) == [newlink, ack]);
+    let f = mnl::Filter::new().attr_u32(16, libc::IFLA_TXQLEN, 1001);
+    assert!(filtered_getlink(f) == [ack]);
+
+    // the farthest jump of nlmsg_type() is 255
+    let mut types = (0x1000..0x10ff).collect::<Vec<u16>>();
+    types.push(libc::RTM_NEWLINK);
+    assert!(filtered_getlink(mnl::Filter::new().nlmsg_type(&types)) == [newlink, ack]);
+    types.insert(0, 0x0fff);
+    match mnl::Filter::new().nlmsg_type(&types).build() {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::E2BIG))),
+        Ok(_) => panic!("must be E2BIG"),
+    }
+
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    nls.attach_filter(&mnl::Filter::new().build().unwrap())
+        .unwrap();
+    nls.detach_filter().unwrap();
+}
+
+#[test]
+fn event_listener() {
+    let ns = std::thread::spawn(|| {
+        assert!(unsafe { libc::unshare(libc::CLONE_NEWNET) } == 0);
+        std::fs::File::open("/proc/thread-self/ns/net").unwrap()
+    })
+    .join()
+    .unwrap();
+    let nsref = mnl::NetnsRef::Fd(ns.as_raw_fd());
+    let mut nls = Socket::open_in_netns(libc::NETLINK_ROUTE, 0, nsref).unwrap();
+    nls.bind(libc::RTMGRP_LINK as u32, mnl::SOCKET_AUTOPID)
+        .unwrap();
+    nls.set_rcvbuf(0).unwrap();
+    let mut ctl = Socket::open_in_netns(libc::NETLINK_ROUTE, 0, nsref).unwrap();
+    ctl.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 0);
+    let mut listener = mnl::EventListener::new(nls).resync_with(nlv);
+    listener.resync().unwrap();
+    assert!(!listener.is_synced());
+    match listener.next_event().unwrap() {
+        mnl::Event::Resync(msg) => assert!(msg.msghdr().nlmsg_type == libc::RTM_NEWLINK),
+        ev => panic!("unexpected: {:?}", ev),
+    }
+    assert!(matches!(listener.next_event().unwrap(), mnl::Event::Synced));
+    assert!(listener.is_synced());
+
+    // toggle lo to overrun the receive buffer
+    for i in 0..64 {
+        let mut nlv = MsgVec::new();
+        nlv.put_header().nlmsg_type = libc::RTM_NEWLINK;
+        let ifi = nlv.put_extra_header::<[i32; 4]>().unwrap();
+        ifi[1] = 1;
+        ifi[2] = if i % 2 == 0 { libc::IFF_UP } else { 0 };
+        ifi[3] = libc::IFF_UP;
+        ctl.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)).unwrap();
+    }
+    let mut nmsg = 0;
+    loop {
+        match listener.next_event().unwrap() {
+            mnl::Event::Msg(_) => nmsg += 1,
+            mnl::Event::Desync => break,
+            ev => panic!("unexpected: {:?}", ev),
+        }
+    }
+    assert!(nmsg < 64);
+    assert!(!listener.is_synced());
+    let mut nresync = 0;
+    loop {
+        match listener.next_event().unwrap() {
+            mnl::Event::Resync(_) => nresync += 1,
+            mnl::Event::Synced => break,
+            // notifications before the dump
+            mnl::Event::Msg(_) => assert!(nresync == 0),
+            ev => panic!("unexpected: {:?}", ev),
+        }
+    }
+    assert!(nresync == 1);
+    assert!(listener.is_synced());
+
+    // without a resync request
+    let mut listener = mnl::EventListener::new(listener.into_inner());
+    assert!(listener.resync().is_err());
+}
+
+// sends nlv to portid from nl, as if it were the kernel.
+fn send_to_portid(nl: &Socket, nlv: &MsgVec, portid: u32) {
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as u16;
+    addr.nl_pid = portid;
+    let buf = nlv.as_ref();
+    let ret = unsafe {
+        libc::sendto(
+            nl.as_raw_fd(),
+            buf.as_ptr() as *const libc::c_void,
+            buf.len(),
+            0,
+            &addr as *const _ as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    assert!(ret == buf.len() as isize);
+}
+
+// puts a reply of the dump seq to portid, and NLMSG_DONE.
+fn put_dump_reply(nlv: &mut MsgVec, portid: u32, seq: u32, flags: u16) {
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::RTM_NEWLINK;
+    nlh.nlmsg_flags = libc::NLM_F_MULTI as u16 | flags;
+    nlh.nlmsg_seq = seq;
+    nlh.nlmsg_pid = portid;
+    nlv.put_extra_header::<[i32; 4]>().unwrap();
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::NLMSG_DONE as u16;
+    nlh.nlmsg_flags = libc::NLM_F_MULTI as u16 | flags;
+    nlh.nlmsg_seq = seq;
+    nlh.nlmsg_pid = portid;
+    *nlv.put_extra_header::<i32>().unwrap() = 0;
+}
+
+#[test]
+fn event_listener_dump_intr() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    nls.set_nonblock().unwrap();
+    let portid = nls.portid();
+    let seq = nls.next_seq();
+    let mut ctl = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    ctl.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+
+    // the kernel does not reply to NLMSG_NOOP without NLM_F_ACK, then the
+    // replies are sent by ctl instead
+    let mut nlv = MsgVec::new();
+    nlv.put_header().nlmsg_type = libc::NLMSG_NOOP as u16;
+    let mut listener = mnl::EventListener::new(nls).resync_with(nlv);
+    listener.resync().unwrap();
+
+    // DUMP_INTR and NLMSG_DONE in a datagram
+    let mut nlv = MsgVec::new();
+    put_dump_reply(&mut nlv, portid, seq + 1, libc::NLM_F_DUMP_INTR as u16);
+    send_to_portid(&ctl, &nlv, portid);
+    nlv.reset();
+    put_dump_reply(&mut nlv, portid, seq + 2, 0);
+    send_to_portid(&ctl, &nlv, portid);
+
+    assert!(matches!(listener.next_event().unwrap(), mnl::Event::Desync));
+    assert!(!listener.is_synced());
+    match listener.next_event().unwrap() {
+        mnl::Event::Resync(msg) => assert!(msg.msghdr().nlmsg_seq == seq + 2),
+        ev => panic!("unexpected: {:?}", ev),
+    }
+    assert!(matches!(listener.next_event().unwrap(), mnl::Event::Synced));
+    assert!(listener.is_synced());
+
+    // without a resync request
+    let mut listener = mnl::EventListener::new(listener.into_inner());
+    let mut nlv = MsgVec::new();
+    nlv.put_header().nlmsg_type = libc::NLMSG_OVERRUN as u16;
+    send_to_portid(&ctl, &nlv, portid);
+    assert!(matches!(listener.next_event().unwrap(), mnl::Event::Desync));
+    assert!(!listener.is_synced());
+    assert!(listener.next_event().unwrap_err().errno() == Some(mnl::Errno(libc::EAGAIN)));
+    assert!(!listener.is_synced());
+}
+
+#[test]
+fn socket_groups() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(libc::RTMGRP_LINK as u32, mnl::SOCKET_AUTOPID)
+        .unwrap();
+    // in u32, not in bytes
+    let words = nls.list_membership().unwrap();
+    assert!(words.len() * 32 >= libc::RTNLGRP_STATS as usize && words.len() <= 4);
+
+    assert!(nls.memberships().unwrap().iter().collect::<Vec<_>>() == [libc::RTNLGRP_LINK]);
+    assert!(nls.join(libc::RTNLGRP_IPV4_ROUTE).unwrap() == libc::RTNLGRP_IPV4_ROUTE);
+    {
+        let guard = nls.join_guard(libc::RTNLGRP_STATS).unwrap();
+        assert!(guard.group() == libc::RTNLGRP_STATS);
+        let groups = nls.memberships().unwrap();
+        assert!(groups.len() == 3);
+        assert!(groups.contains(libc::RTNLGRP_STATS));
+        assert!(!groups.contains(libc::RTNLGRP_NEIGH));
+    }
+    let groups = nls.memberships().unwrap();
+    assert!(!groups.contains(libc::RTNLGRP_STATS));
+    nls.leave(libc::RTNLGRP_IPV4_ROUTE).unwrap();
+    assert!(nls.memberships().unwrap().iter().collect::<Vec<_>>() == [libc::RTNLGRP_LINK]);
+
+    let mut nls = Socket::open(libc::NETLINK_GENERIC, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    assert!(nls.memberships().unwrap().is_empty());
+    let id = nls.join(("nlctrl", "notify")).unwrap();
+    assert!(nls.memberships().unwrap().contains(id));
+    match nls.join(("nlctrl", "no such group")) {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::ENOENT))),
+        Ok(_) => panic!("must be ENOENT"),
+    }
+}
+
+#[test]
+fn msgvec_nest_guard() {
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    let len = nlv.len();
+
+    // cancelled on error
+    let ret = nlv.nest(1u16, |nlv| {
+        nlv.put(2u16, &3u32)?;
+        Err(mnl::Errno(libc::EINVAL).into())
+    });
+    assert!(ret.is_err());
+    assert!(nlv.len() == len && nlv.nlmsg_len() == len as u32 && nlv.nest_depth() == 0);
+
+    // cancelled on panic
+    let ret = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        nlv.nest(1u16, |nlv| {
+            nlv.put(2u16, &3u32)?;
+            panic!("in nest");
+        })
+        .map(|_| ())
+    }));
+    assert!(ret.is_err());
+    assert!(nlv.len() == len && nlv.nest_depth() == 0);
+
+    // an inner nest is not ended
+    let mut outer = nlv.nest_guard(1u16).unwrap();
+    outer.nest_start(2u16).unwrap();
+    assert!(outer.end().is_err());
+    assert!(nlv.len() == len && nlv.nest_depth() == 0);
+
+    let mut outer = nlv.nest_guard(1u16).unwrap();
+    outer.put(2u16, &3u32).unwrap();
+    let mut inner = outer.nest_guard(4u16).unwrap();
+    inner.put(5u16, &6u32).unwrap();
+    inner.cancel().unwrap();
+    outer.end().unwrap();
+    assert!(nlv.len() == len + 12);
+    let nlh = Msghdr::from_bytes(nlv.finalize().unwrap()).unwrap();
+    let attr = nlh.attrs(0).next().unwrap().unwrap();
+    assert!(attr.atype() == 1 && attr.payload_len() == 8);
+
+    // can not be sent with a nest open
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    nlv.nest_start(libc::IFLA_LINKINFO).unwrap();
+    assert!(nlv.finalize().is_err());
+    match nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)) {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::EINPROGRESS))),
+        Ok(_) => panic!("must be EINPROGRESS"),
+    }
+    assert!(nls.send_batch(&[&nlv]).is_err());
+    nlv.nest_end().unwrap();
+    nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)).unwrap();
+}
+
+#[test]
+fn msgvec_bounded() {
+    let errno = |ret: mnl::Result<&mut MsgVec>| match ret {
+        Err(err) => err.errno().unwrap().0,
+        Ok(_) => 0,
+    };
+
+    // nla_len overflows regardless of the bound
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    let big = vec![0u8; 70000];
+    assert!(errno(nlv.put_bytes(1u16, &big)) == libc::EOVERFLOW);
+    assert!(errno(nlv.put(1u16, &[0u8; 65532])) == libc::EOVERFLOW);
+    assert!(nlv.len() == 16 && nlv.nlmsg_len() == 16);
+    nlv.put_bytes(1u16, &big[..65531]).unwrap();
+    assert!(nlv.len() == 16 + 65536);
+    nlv.reset();
+
+    // a nest too large is left open
+    nlv.put_header();
+    nlv.nest_start(1u16).unwrap();
+    nlv.put_bytes(2u16, &big[..40000]).unwrap();
+    nlv.put_bytes(2u16, &big[..40000]).unwrap();
+    assert!(errno(nlv.nest_end()) == libc::EOVERFLOW);
+    assert!(nlv.nest_depth() == 1);
+    nlv.nest_cancel().unwrap();
+    assert!(nlv.len() == 16 && nlv.nlmsg_len() == 16);
+    let ret = nlv.nest(1u16, |nlv| {
+        nlv.put_bytes(2u16, &big[..40000])?;
+        nlv.put_bytes(2u16, &big[..40000])?;
+        Ok(())
+    });
+    assert!(ret.is_err());
+    assert!(nlv.len() == 16 && nlv.nest_depth() == 0);
+
+    // bounded
+    let mut nlv = MsgVec::bounded(64);
+    assert!(nlv.max_size() == Some(64));
+    nlv.try_put_header().unwrap();
+    nlv.put(1u16, &2u32).unwrap();
+    nlv.nest_start(3u16).unwrap();
+    nlv.put_str(4u16, "0123456789").unwrap();
+    assert!(nlv.len() == 44);
+    assert!(errno(nlv.put_cstr(4u16, "0123456789abcdef")) == libc::ENOSPC);
+    assert!(nlv.len() == 44 && nlv.nlmsg_len() == 44);
+    nlv.nest_end().unwrap();
+    assert!(
+        errno(
+            nlv.nest_start(5u16)
+                .and_then(|nlv| nlv.put_bytes(6u16, &[0u8; 16]))
+        ) == libc::ENOSPC
+    );
+    nlv.nest_cancel().unwrap();
+    nlv.put(6u16, &7u64).unwrap();
+    assert!(errno(nlv.put(6u16, &7u64)) == libc::ENOSPC);
+    nlv.put_flag(8u16).unwrap();
+    assert!(nlv.len() == 60 && nlv.nlmsg_len() == 60);
+    match nlv.try_put_header() {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::ENOSPC))),
+        Ok(_) => panic!("must be ENOSPC"),
+    }
+    assert!(nlv.finalize().unwrap().len() == 60);
+
+    // put_header() exceeds, but it can not be sent
+    nlv.put_header();
+    assert!(nlv.len() == 76);
+    assert!(nlv.finalize().is_err());
+    assert!(nlv.set_max_size(Some(64)).is_err());
+    nlv.set_max_size(None).unwrap();
+    assert!(nlv.finalize().unwrap().len() == 76);
+}
+
+#[test]
+fn batch_writer() {
+    let mut nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let mut writer = mnl::BatchWriter::new(128);
+    let mut nlv = MsgVec::new();
+    for seq in 1..11 {
+        rtnl_getlink(&mut nlv, if seq == 5 { i32::MAX } else { 1 });
+        let nlh = nlv.header_mut().unwrap();
+        nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+        nlh.nlmsg_seq = seq;
+    }
+    writer.push(&nlv).unwrap();
+    // 32 bytes each
+    assert!(writer.chunks() == 3);
+    assert!(writer.chunk(0).unwrap().seqs() == [1, 2, 3, 4]);
+    assert!(writer.chunk(2).unwrap().msgvec().len() == 64);
+    assert!(writer.chunk_of(5) == Some(1));
+    assert!(writer.chunk_of(11).is_none());
+
+    // nothing is pushed on error
+    let mut nlv = MsgVec::new();
+    rtnl_getlink(&mut nlv, 1);
+    nlv.put_bytes(libc::IFLA_IFNAME, &[0u8; 100]).unwrap();
+    match writer.push(&nlv) {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::EMSGSIZE))),
+        Ok(_) => panic!("must be EMSGSIZE"),
+    }
+    nlv.reset();
+    rtnl_getlink(&mut nlv, 1);
+    nlv.nest_start(libc::IFLA_LINKINFO).unwrap();
+    assert!(writer.push(&nlv).is_err());
+    assert!(writer.chunks() == 3 && writer.chunk(2).unwrap().seqs() == [9, 10]);
+
+    let acks = writer.flush(&nls).unwrap();
+    assert!(writer.is_empty());
+    assert!(acks.len() == 3);
+    assert!(acks[1].seqs == [5, 6, 7, 8]);
+    assert!(acks[0].is_ok() && !acks[1].is_ok() && acks[2].is_ok());
+    assert!(acks[2].results.len() == 2);
+    let errors = acks[1].errors().collect::<Vec<_>>();
+    assert!(errors.len() == 1 && errors[0].0 == 5);
+    assert!(errors[0].1.errno() == Some(mnl::Errno(libc::ENODEV)));
+    assert!(writer.send_next(&nls).unwrap().is_none());
+
+    // kept until abandoned, on the replies overrun
+    nls.set_rcvbuf(0).unwrap();
+    let mut nlv = MsgVec::new();
+    for seq in 11..15 {
+        rtnl_getlink(&mut nlv, 1);
+        let nlh = nlv.header_mut().unwrap();
+        nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+        nlh.nlmsg_seq = seq;
+    }
+    writer.push(&nlv).unwrap();
+    assert!(writer.chunks() == 1);
+    match writer.send_next(&nls) {
+        Err(err) => assert!(err.errno() == Some(mnl::Errno(libc::ENOBUFS))),
+        Ok(_) => panic!("must be ENOBUFS"),
+    }
+    assert!(writer.chunks() == 0 && !writer.is_empty());
+    let unacked = writer.unacked();
+    assert!(!unacked.is_empty() && unacked.iter().all(|seq| (11..15).contains(seq)));
+    let acks = writer.abandon().unwrap();
+    assert!(acks.seqs == [11, 12, 13, 14]);
+    assert!(acks.results.len() + unacked.len() == 4);
+    assert!(writer.is_empty() && writer.abandon().is_none());
+
+    // drop the replies left, then the socket is no longer congested
+    nls.set_rcvbuf(1 << 16).unwrap();
+    nls.set_nonblock().unwrap();
+    let mut buf = mnl::RecvBuf::new(mnl::SOCKET_DUMP_SIZE);
+    while nls.recvfrom(&mut buf).is_ok() {}
+    nlv.reset();
+    rtnl_getlink(&mut nlv, 1);
+    let nlh = nlv.header_mut().unwrap();
+    nlh.nlmsg_flags = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+    nlh.nlmsg_seq = 15;
+    writer.push(&nlv).unwrap();
+    let acks = writer.send_next(&nls).unwrap().unwrap();
+    assert!(acks.results.len() == 1 && acks.results[0].0 == 15 && acks.is_ok());
+}
+
+// linux/netfilter/nf_tables.h
+const NFT_MSG_NEWTABLE: u16 = 0;
+const NFT_MSG_GETTABLE: u16 = 1;
+const NFT_MSG_DELTABLE: u16 = 2;
+const NFTA_TABLE_NAME: u16 = 1;
+
+fn nft_table(nlv: &mut MsgVec, name: &str) {
+    let nfg = nlv.put_extra_header::<[u8; 4]>().unwrap();
+    nfg[0] = libc::NFPROTO_INET as u8;
+    nlv.put_cstr(NFTA_TABLE_NAME, name).unwrap();
+}
+
+fn nft_table_batch(batch: &mut mnl::NfnlBatch, name: &str) {
+    let nfg = batch.put_extra_header::<[u8; 4]>().unwrap();
+    nfg[0] = libc::NFPROTO_INET as u8;
+    batch.put_cstr(NFTA_TABLE_NAME, name).unwrap();
+}
+
+fn nft_table_exists(nls: &mut Socket, name: &str) -> bool {
+    let mut nlv = MsgVec::new();
+    nlv.put_header().nlmsg_type = (libc::NFNL_SUBSYS_NFTABLES << 8) as u16 | NFT_MSG_GETTABLE;
+    nft_table(&mut nlv, name);
+    match nls.request(&mut nlv, |_| Ok(mnl::CbStatus::Ok)) {
+        Ok(_) => true,
+        Err(err) if err.errno() == Some(mnl::Errno(libc::ENOENT)) => false,
+        Err(err) => panic!("GETTABLE: {}", err),
+    }
+}
+
+#[test]
+fn nfnl_batch() {
+    let ns = std::thread::spawn(|| {
+        assert!(unsafe { libc::unshare(libc::CLONE_NEWNET) } == 0);
+        std::fs::File::open("/proc/thread-self/ns/net").unwrap()
+    })
+    .join()
+    .unwrap();
+    let nsref = mnl::NetnsRef::Fd(ns.as_raw_fd());
+    let mut nls = Socket::open_in_netns(libc::NETLINK_NETFILTER, 0, nsref).unwrap();
+    nls.bind(0, mnl::SOCKET_AUTOPID).unwrap();
+    let subsys = libc::NFNL_SUBSYS_NFTABLES as u16;
+    let mtype = |t: u16| subsys << 8 | t;
+
+    let seq = nls.next_seq();
+    let mut batch = mnl::NfnlBatch::begin(MsgVec::new(), subsys, seq).unwrap();
+    assert!(batch.len() == 20 && batch.seq() == seq + 1);
+    let nlh = batch.put_header().unwrap();
+    nlh.nlmsg_type = mtype(NFT_MSG_NEWTABLE);
+    nlh.nlmsg_flags |= libc::NLM_F_CREATE as u16;
+    nft_table_batch(&mut batch, "t0");
+    batch.put_header().unwrap().nlmsg_type = mtype(NFT_MSG_DELTABLE);
+    nft_table_batch(&mut batch, "no such table");
+    assert!(batch.commit(&nls).is_err());
+    batch.end().unwrap();
+    assert!(batch.end().is_err());
+    assert!(batch.find(seq + 3).unwrap().nlmsg_type == libc::NFNL_MSG_BATCH_END as u16);
+
+    // the failed message, and the whole is aborted
+    let failed = batch.commit(&nls).unwrap();
+    assert!(failed.len() == 1);
+    assert!(failed[0].0.nlmsg_seq == seq + 2);
+    assert!(failed[0].0.nlmsg_type == mtype(NFT_MSG_DELTABLE));
+    assert!(failed[0].1.errno() == Some(mnl::Errno(libc::ENOENT)));
+    assert!(!nft_table_exists(&mut nls, "t0"));
+
+    // abort before commit
+    batch.abort().unwrap();
+    assert!(batch.len() == 20 && !batch.is_ended());
+    assert!(batch.find(seq + 1).is_none());
+    let nlh = batch.put_header().unwrap();
+    assert!(nlh.nlmsg_seq == seq + 4);
+    nlh.nlmsg_type = mtype(NFT_MSG_NEWTABLE);
+    nft_table_batch(&mut batch, "t1");
+    batch.end().unwrap();
+    assert!(batch.commit(&nls).unwrap().is_empty());
+    assert!(nft_table_exists(&mut nls, "t1"));
+
+    // no message to put to, but the begin and end
+    let mut frame = mnl::NfnlBatch::begin(MsgVec::new(), subsys, 1).unwrap();
+    let err = frame.put_extra_header::<[u8; 4]>().unwrap_err();
+    assert!(err.errno() == Some(mnl::Errno(libc::EINVAL)));
+    assert!(frame.put_flag(1u16).is_err() && frame.header_mut().is_err());
+    frame.put_header().unwrap();
+    assert!(frame.put_flag(1u16).is_ok());
+    frame.end().unwrap();
+    assert!(frame.put_flag(1u16).is_err() && frame.nest_start(1u16).is_err());
+    frame.abort().unwrap();
+    assert!(frame.len() == 20);
+
+    // begin twice, which the subsystem discards without any reply
+    let seq = nls.next_seq();
+    let mut batch = mnl::NfnlBatch::begin(batch.into_inner(), subsys, seq).unwrap();
+    batch.put_header().unwrap().nlmsg_type = libc::NFNL_MSG_BATCH_BEGIN as u16;
+    let nfg = batch.put_extra_header::<[u8; 4]>().unwrap();
+    nfg[2..].copy_from_slice(&subsys.to_be_bytes());
+    batch.put_header().unwrap().nlmsg_type = mtype(NFT_MSG_NEWTABLE);
+    nft_table_batch(&mut batch, "t2");
+    batch.end().unwrap();
+    let err = batch.commit(&nls).unwrap_err();
+    assert!(err.errno() == Some(mnl::Errno(libc::ENOMSG)));
+    assert!(!nft_table_exists(&mut nls, "t2"));
+
+    // no such subsystem
+    let mut batch = mnl::NfnlBatch::begin(batch.into_inner(), 0xff, 1).unwrap();
+    batch.put_header().unwrap().nlmsg_type = 0xff << 8;
+    batch.end().unwrap();
+    assert!(batch.commit(&nls).is_err());
+}
+
+#[test]
+fn recv_buf() {
+    let mut buf = mnl::dump_buffer();
+    assert!(buf.len() == mnl::SOCKET_DUMP_SIZE);
+    assert!(buf.as_ptr() as usize & (mnl::ALIGNTO - 1) == 0);
+
+    let mut heap = mnl::RecvBuf::new(13);
+    assert!(heap.len() == 16);
+    heap.grow(8);
+    assert!(heap.len() == 16);
+    heap.grow(17);
+    assert!(heap.len() == 20);
+    assert!(
+        mnl::RecvBuf::from_storage(vec![0u32; 2])
+            .into_storage()
+            .len()
+            == 2
+    );
+
+    let nls = Socket::open(libc::NETLINK_ROUTE, 0).unwrap();
+    assert!(nls.recvfrom(&mut buf[1..]).unwrap_err().errno() == Some(mnl::Errno(libc::EINVAL)));
+    assert!(
+        mnl::cb_run(&buf[1..], 0, 0, mnl::NOCB).unwrap_err().errno()
+            == Some(mnl::Errno(libc::EINVAL))
+    );
+}
+
 // TODO: no...
 //   sendto, recvfrom
 //   setsockopt, getsockopt
@@ -167,6 +1198,56 @@ fn nlmsg_next_header() {
     assert!(!nlh2.ok(len));
 }
 
+#[test]
+fn nlmsg_messages() {
+    let mut nlv = MsgVec::new();
+    nlv.put_header().nlmsg_type = 0x10;
+    nlv.put(1u16, &0x11u8).unwrap();
+    nlv.put_header().nlmsg_type = 0x20;
+    nlv.put_header().nlmsg_type = 0x30;
+
+    let mut msgs = mnl::Messages::new(nlv.as_ref());
+    assert!(msgs.next().unwrap().unwrap().nlmsg_type == 0x10);
+    assert!(msgs.offset() == 24);
+    assert!(msgs.next().unwrap().unwrap().nlmsg_type == 0x20);
+    assert!(msgs.next().unwrap().unwrap().nlmsg_type == 0x30);
+    assert!(msgs.next().is_none());
+    assert!(mnl::Messages::new(&[]).next().is_none());
+
+    // truncated
+    let len = nlv.len();
+    let mut msgs = mnl::Messages::new(&nlv.as_ref()[..len - 1]);
+    assert!(msgs.next().unwrap().is_ok());
+    assert!(msgs.next().unwrap().is_ok());
+    assert!(msgs.next().unwrap().unwrap_err().errno().unwrap().0 == libc::EBADMSG);
+    assert!(msgs.next().is_none());
+
+    let mut buf = [0u32; 8];
+    set_nlmsg_len(unsafe { &mut *(&mut buf as *mut _ as *mut [u8; 32]) }, 15);
+    let bytes = unsafe { &*(&buf as *const _ as *const [u8; 32]) };
+    assert!(
+        mnl::Messages::new(bytes)
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .errno()
+            .unwrap()
+            .0
+            == libc::EBADMSG
+    );
+    // misaligned
+    assert!(
+        mnl::Messages::new(&bytes[1..])
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .errno()
+            .unwrap()
+            .0
+            == libc::EINVAL
+    );
+}
+
 #[test]
 fn nlmsg_seq_ok() {
     // ignores 0
@@ -559,13 +1640,13 @@ fn nlmsg_nest_cancel() {
 fn parse_cb(mut n: u16) -> Box<dyn FnMut(&Attr) -> mnl::CbResult> {
     Box::new(move |attr: &Attr| {
         if attr.nla_type != n {
-            return Err(mnl::GenError::from(Error::new(
+            return Err(mnl::Error::from(Error::new(
                 ErrorKind::Other,
                 "type is differ",
             )));
         }
         if attr.value::<u8>().unwrap() as u16 != 0x10 + n {
-            return Err(mnl::GenError::from(Error::new(
+            return Err(mnl::Error::from(Error::new(
                 ErrorKind::Other,
                 "value is differ",
             )));
@@ -591,23 +1672,162 @@ fn nlmsg_parse() {
     assert!(nlv.msghdr().unwrap().parse(0, parse_cb(1)).is_err());
 }
 
-// #[test]
-// fn nlmsg_attrs() {
-//     let mut buf = mnl::default_buffer();
-//     let mut nlh = Msghdr::put_header(&mut buf).unwrap();
-//     nlh.put(0u16, &0x10u8).unwrap();
-//     nlh.put(1u16, &0x11u8).unwrap();
-//     nlh.put(2u16, &0x12u8).unwrap();
-//     nlh.put(3u16, &0x13u8).unwrap();
-
-//     let mut i = 0u8;
-//     let mut attrs = nlh.attrs(0).unwrap();
-//     while let Some(attr) = attrs.next() {
-//         assert!(attr.nla_type == i as u16);
-//         assert!(attr.value::<u8>().unwrap() == (0x10 + i));
-//         i += 1;
-//     }
-// }
+#[test]
+fn nlmsg_attrs() {
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(0u16, &0x10u8).unwrap();
+    nlv.put(1u16, &0x11u8).unwrap();
+    nlv.put(2u16, &0x12u8).unwrap();
+    nlv.put(3u16, &0x13u8).unwrap();
+
+    let mut i = 0u8;
+    for attr in nlv.msghdr().unwrap().attrs(0) {
+        let attr = attr.unwrap();
+        assert!(attr.nla_type == i as u16);
+        assert!(attr.value::<u8>().unwrap() == (0x10 + i));
+        i += 1;
+    }
+    assert!(i == 4);
+    assert!(nlv.msghdr().unwrap().attrs(32).count() == 0);
+    assert!(nlv.msghdr().unwrap().attrs(33).next().unwrap().is_err());
+
+    // malformed, nla_len exceeds the message
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(1u16, &0x11u8).unwrap();
+    nlv.put(2u16, &0x12u8).unwrap();
+    let mut buf = nlv.as_ref().to_vec();
+    set_buf(&mut buf, 24, 9u16);
+    let nlh = mnl::Messages::new(&buf).next().unwrap().unwrap();
+    let mut attrs = nlh.attrs(0);
+    assert!(attrs.next().unwrap().is_ok());
+    assert!(attrs.next().unwrap().unwrap_err().errno().unwrap().0 == libc::EBADMSG);
+    assert!(attrs.next().is_none());
+    assert!(nlh.parse(0, |_| Ok(mnl::CbStatus::Ok)).is_err());
+}
+
+#[test]
+fn attr_nested() {
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.nest_start(1u16).unwrap();
+    nlv.put(2u16, &0x12u8).unwrap();
+    nlv.put_flag(3u16).unwrap();
+    nlv.nest_end().unwrap();
+
+    let nest = nlv.msghdr().unwrap().attrs(0).next().unwrap().unwrap();
+    let types: Vec<u16> = nest.nested().map(|a| a.unwrap().atype()).collect();
+    assert!(types == [2, 3]);
+    assert!(nest
+        .nested()
+        .filter_map(Result::ok)
+        .find(|a| a.atype() == 2)
+        .is_some());
+}
+
+#[test]
+fn attr_policy() {
+    use mnl::{AttrDataType, AttrPolicy, Policy, PolicyError, PolicyViolation};
+
+    static NESTED: Policy = Policy::new(&[
+        (2, AttrPolicy::new(AttrDataType::NulString).max_len(3)),
+        (3, AttrPolicy::new(AttrDataType::Flag)),
+    ])
+    .reject_unknown();
+    static POLICY: Policy = Policy::new(&[
+        (1, AttrPolicy::new(AttrDataType::U32).range(1, 100)),
+        (4, AttrPolicy::nested(&NESTED)),
+    ]);
+
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(1u16, &10u32).unwrap();
+    nlv.put(5u16, &0u8).unwrap(); // unknown, but allowed
+    nlv.nest_start(4u16).unwrap();
+    nlv.put_cstr(2u16, "abc").unwrap();
+    nlv.put_flag(3u16).unwrap();
+    nlv.nest_end().unwrap();
+    let mut types = Vec::new();
+    assert!(nlv
+        .msghdr()
+        .unwrap()
+        .parse_with_policy(0, &POLICY, |attr| {
+            types.push(attr.atype());
+            Ok(mnl::CbStatus::Ok)
+        })
+        .is_ok());
+    assert!(types == [1, 5, 4]);
+
+    let check = |nlv: &MsgVec| -> PolicyError {
+        let ret = nlv
+            .msghdr()
+            .unwrap()
+            .parse_with_policy(0, &POLICY, |_| panic!("must not be called"));
+        policy_error(ret.unwrap_err())
+    };
+
+    // out of range
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(1u16, &101u32).unwrap();
+    let err = check(&nlv);
+    assert!(err.path == [1]);
+    assert!(err.offset == Msghdr::HDRLEN);
+    assert!(
+        err.violation
+            == PolicyViolation::OutOfRange {
+                value: 101,
+                min: 1,
+                max: 100
+            }
+    );
+    assert!(err.errno().0 == libc::ERANGE);
+
+    // invalid length
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(1u16, &1u16).unwrap();
+    assert!(
+        check(&nlv).violation
+            == PolicyViolation::InvalidLength {
+                expected: 4,
+                len: 2
+            }
+    );
+
+    // too long string in the nest
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(1u16, &1u32).unwrap();
+    nlv.nest_start(4u16).unwrap();
+    nlv.put_cstr(2u16, "abcd").unwrap();
+    nlv.nest_end().unwrap();
+    let err = check(&nlv);
+    assert!(err.path == [4, 2]);
+    assert!(err.offset == Msghdr::HDRLEN + Attr::HDRLEN + 4 + Attr::HDRLEN);
+    assert!(err.violation == PolicyViolation::TooLong { max: 3, len: 4 });
+
+    // unknown in the nest which rejects unknown
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.nest_start(4u16).unwrap();
+    nlv.put_flag(3u16).unwrap();
+    nlv.put_flag(9u16).unwrap();
+    nlv.nest_end().unwrap();
+    let err = check(&nlv);
+    assert!(err.path == [4, 9]);
+    assert!(err.violation == PolicyViolation::UnknownType);
+
+    // nested one, offset is from the nest
+    let nest = nlv.msghdr().unwrap().attrs(0).next().unwrap().unwrap();
+    let err = policy_error(
+        nest.parse_nested_with_policy(&NESTED, |_| Ok(mnl::CbStatus::Ok))
+            .unwrap_err(),
+    );
+    assert!(err.path == [9]);
+    assert!(err.offset == Attr::HDRLEN * 2);
+}
 
 // #[test]
 // fn nlmsg_batch_construct() {
@@ -686,6 +1906,61 @@ fn nlmsg_parse() {
 //     assert!(b.is_empty());
 // }
 
+#[derive(rsmnl_derive::AttrTbl)]
+struct NestTbl<'a> {
+    #[nla(type = 1u16, data = U8)]
+    u8attr: Option<&'a Attr<'a>>,
+}
+
+#[derive(rsmnl_derive::AttrTbl)]
+struct DeriveTbl<'a> {
+    #[nla(type = 1u16, data = U32)]
+    u32attr: Option<&'a Attr<'a>>,
+    #[nla(type = 2u16, data = NulString)]
+    cstr: Option<&'a Attr<'a>>,
+    #[nla(type = 3u16, data = Flag)]
+    flag: Option<&'a Attr<'a>>,
+    #[nla(type = 4u16, data = Binary, value = std::net::Ipv4Addr)]
+    addr: Option<&'a Attr<'a>>,
+    #[nla(type = 5u16, nested = NestTbl)]
+    nest: Option<&'a Attr<'a>>,
+    #[nla(type = 6u16, data = Binary)]
+    bytes: Option<&'a Attr<'a>>,
+}
+
+#[test]
+fn attr_derive_tbl() {
+    let mut nlv = MsgVec::new();
+    nlv.put_header();
+    nlv.put(1u16, &1234u32).unwrap();
+    nlv.put_cstr(2u16, "eth0").unwrap();
+    nlv.put(4u16, &std::net::Ipv4Addr::new(192, 0, 2, 1))
+        .unwrap();
+    nlv.nest_start(5u16).unwrap();
+    nlv.put(1u16, &56u8).unwrap();
+    nlv.nest_end().unwrap();
+    nlv.put_bytes(6u16, &[]).unwrap();
+
+    let tb = DeriveTbl::try_from_nlmsg(0, nlv.msghdr().unwrap()).unwrap();
+    assert!(tb.validate().is_ok());
+    assert!(tb.u32attr().unwrap() == Some(1234));
+    assert!(tb.cstr().unwrap() == Some("eth0"));
+    assert!(!tb.flag().unwrap());
+    assert!(tb.addr().unwrap() == Some(std::net::Ipv4Addr::new(192, 0, 2, 1)));
+    assert!(tb.nest().unwrap().unwrap().u8attr().unwrap() == Some(56));
+    assert!(tb.bytes().unwrap() == Some(&[][..]));
+
+    // unknown attribute type and invalid length
+    nlv.put(7u16, &0u8).unwrap();
+    nlv.put_flag(3u16).unwrap();
+    nlv.put(1u16, &1234u16).unwrap();
+    assert!(DeriveTbl::try_from_nlmsg(0, nlv.msghdr().unwrap()).is_err());
+    let tb = DeriveTbl::from_nlmsg(0, nlv.msghdr().unwrap()).unwrap();
+    assert!(tb.flag().unwrap());
+    assert!(tb.u32attr().unwrap_err().errno().unwrap().0 == libc::ERANGE);
+    assert!(tb.validate().is_err());
+}
+
 fn nlmsg_cb_ok(_: &Msghdr) -> mnl::CbResult {
     Ok(mnl::CbStatus::Ok)
 }
@@ -695,7 +1970,7 @@ fn nlmsg_cb_stop(_: &Msghdr) -> mnl::CbResult {
 }
 
 fn nlmsg_cb_error(_: &Msghdr) -> mnl::CbResult {
-    Err(mnl::GenError::from(Error::new(ErrorKind::Other, "error")))
+    Err(mnl::Error::from(Error::new(ErrorKind::Other, "error")))
 }
 
 #[test]
@@ -724,6 +1999,174 @@ fn nlmsg_cb_run() {
     assert!(
         mnl::cb_run2(nlv.as_ref(), 0, 0, mnl::NOCB, &mut ctlcbs).unwrap() == mnl::CbStatus::Stop
     );
+
+    ctlcbs[libc::NLMSG_DONE as usize] = Some(nlmsg_cb_ok);
+    let len = nlv.len();
+    assert!(mnl::cb_run2(&nlv.as_ref()[..len - 4], 0, 0, mnl::NOCB, &mut ctlcbs).is_err());
+    assert!(mnl::cb_run(&[], 0, 0, mnl::NOCB).is_err());
+}
+
+#[test]
+fn cb_run_error() {
+    let mut nlv = MsgVec::new();
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::NLMSG_MIN_TYPE as u16;
+    nlh.nlmsg_seq = 1;
+    nlh.nlmsg_pid = 2;
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::NLMSG_MIN_TYPE as u16;
+    nlh.nlmsg_flags = libc::NLM_F_DUMP_INTR as u16;
+    let buf = nlv.as_ref();
+
+    match mnl::cb_run(buf, 3, 0, Some(nlmsg_cb_ok)) {
+        Err(mnl::Error::SeqMismatch {
+            expected: 3,
+            actual: 1,
+        }) => {}
+        ret => panic!("unexpected: {:?}", ret),
+    }
+    match mnl::cb_run(buf, 0, 4, Some(nlmsg_cb_ok)) {
+        Err(mnl::Error::PortidMismatch {
+            expected: 4,
+            actual: 2,
+        }) => {}
+        ret => panic!("unexpected: {:?}", ret),
+    }
+    match mnl::cb_run(buf, 1, 2, Some(nlmsg_cb_ok)) {
+        Err(mnl::Error::DumpInterrupted) => {}
+        ret => panic!("unexpected: {:?}", ret),
+    }
+    match mnl::cb_run(&buf[..buf.len() - 1], 1, 2, Some(nlmsg_cb_ok)) {
+        Err(mnl::Error::Malformed { offset: 16 }) => {}
+        ret => panic!("unexpected: {:?}", ret),
+    }
+
+    let err = mnl::cb_run(buf, 0, 0, Some(nlmsg_cb_error)).unwrap_err();
+    assert!(format!("{}", err) == "error");
+    assert!(err.errno().is_none());
+    let err = mnl::Error::from(Error::from_raw_os_error(libc::ENOENT));
+    assert!(err.errno().unwrap().0 == libc::ENOENT);
+    assert!(Error::from(err).raw_os_error() == Some(libc::ENOENT));
+    let err: Error = mnl::Error::DumpInterrupted.into();
+    assert!(err.kind() == Error::from_raw_os_error(libc::EINTR).kind());
+}
+
+#[test]
+fn nlmsg_cb_run_ext_ack() {
+    // NLM_F_CAPPED | NLM_F_ACK_TLVS
+    let mut nlv = MsgVec::new();
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::NLMSG_ERROR as u16;
+    nlh.nlmsg_flags = 0x300;
+    let err = nlv.put_extra_header::<libc::nlmsgerr>().unwrap();
+    err.error = -libc::EINVAL;
+    err.msg.nlmsg_len = 32;
+    err.msg.nlmsg_type = 0x10;
+    nlv.put_cstr(1u16, "invalid MTU").unwrap(); // NLMSGERR_ATTR_MSG
+    nlv.put(2u16, &20u32).unwrap(); // NLMSGERR_ATTR_OFFS
+    nlv.nest_start(4u16).unwrap(); // NLMSGERR_ATTR_POLICY
+    nlv.put(1u16, &3u32).unwrap(); // NL_POLICY_TYPE_ATTR_TYPE
+    nlv.put(7u16, &16u32).unwrap(); // NL_POLICY_TYPE_ATTR_MAX_LENGTH
+    nlv.nest_end().unwrap();
+    nlv.put(5u16, &3u32).unwrap(); // NLMSGERR_ATTR_MISS_TYPE
+
+    let ret = mnl::cb_run(nlv.as_ref(), 0, 0, mnl::NOCB).unwrap_err();
+    let err = kernel_error(ret);
+    assert!(err.errno.0 == libc::EINVAL);
+    assert!(err.request.len() == Msghdr::HDRLEN);
+    assert!(err.ext_ack.msg.as_ref().unwrap() == "invalid MTU");
+    assert!(err.ext_ack.offset == Some(20));
+    assert!(err.ext_ack.miss_type == Some(3));
+    assert!(err.ext_ack.miss_nest.is_none());
+    let policy = err.ext_ack.policy.as_ref().unwrap();
+    assert!(policy.atype == Some(3));
+    assert!(policy.max_length == Some(16));
+    assert!(policy.min_length.is_none());
+    assert!(format!("{}", err).starts_with("invalid MTU: "));
+
+    // not capped, the whole request is echoed
+    let mut nlv = MsgVec::new();
+    let nlh = nlv.put_header();
+    nlh.nlmsg_type = libc::NLMSG_ERROR as u16;
+    nlh.nlmsg_flags = 0x200;
+    let err = nlv.put_extra_header::<libc::nlmsgerr>().unwrap();
+    err.error = -libc::ENOENT;
+    err.msg.nlmsg_len = 24;
+    nlv.put_extra_header::<[u8; 8]>().unwrap()[0] = 0xff;
+    nlv.put(2u16, &16u32).unwrap();
+    let err = kernel_error(mnl::cb_run(nlv.as_ref(), 0, 0, mnl::NOCB).unwrap_err());
+    assert!(err.errno.0 == libc::ENOENT);
+    assert!(err.request.len() == 24);
+    assert!(err.request[Msghdr::HDRLEN] == 0xff);
+    assert!(err.ext_ack.offset == Some(16));
+    assert!(err.ext_ack.msg.is_none());
+
+    // no TLVs
+    let mut nlv = MsgVec::new();
+    nlv.put_header().nlmsg_type = libc::NLMSG_ERROR as u16;
+    nlv.put_extra_header::<libc::nlmsgerr>().unwrap().error = -libc::EPERM;
+    let err = kernel_error(mnl::cb_run(nlv.as_ref(), 0, 0, mnl::NOCB).unwrap_err());
+    assert!(err.errno.0 == libc::EPERM);
+    assert!(err.ext_ack == mnl::ExtAck::default());
+}
+
+#[test]
+fn nlmsg_attr_path() {
+    let mut nlv = MsgVec::new();
+    nlv.put_header().nlmsg_seq = 1;
+    nlv.put(1u16, &1u32).unwrap();
+    let nlh = nlv.put_header();
+    nlh.nlmsg_seq = 2;
+    nlv.put_extra_header::<u32>().unwrap();
+    nlv.put(1u16, &1u32).unwrap();
+    nlv.nest_start(2u16).unwrap();
+    nlv.nest_start(3u16).unwrap();
+    nlv.put(4u16, &4u16).unwrap();
+    nlv.put(5u16, &0x55u32).unwrap();
+    nlv.nest_end().unwrap();
+    nlv.nest_end().unwrap();
+
+    assert!(nlv.find(3).is_none());
+    assert!(nlv.find(1).unwrap().nlmsg_len == 24);
+    let nlh = nlv.find(2).unwrap();
+    // header + extra header + attr 1 + nest 2 + nest 3 + attr 4
+    let offset = Msghdr::HDRLEN + 4 + 8 + 4 + 4 + 8;
+    let path = nlh.attr_path(4, offset).unwrap();
+    let types: Vec<u16> = path.iter().map(|a| a.atype()).collect();
+    assert!(types == [2, 3, 5]);
+    assert!(path[2].value::<u32>().unwrap() == 0x55);
+    // inside the value
+    let types: Vec<u16> = nlh
+        .attr_path(4, offset + 6)
+        .unwrap()
+        .iter()
+        .map(|a| a.atype())
+        .collect();
+    assert!(types == [2, 3, 5]);
+    // nest itself
+    assert!(nlh.attr_path(4, Msghdr::HDRLEN + 12).unwrap().len() == 1);
+    // extra header
+    assert!(nlh.attr_path(4, Msghdr::HDRLEN).is_none());
+    assert!(nlh.attr_path(4, nlh.nlmsg_len as usize).is_none());
+
+    // from the error the kernel reports
+    let mut errv = MsgVec::new();
+    let errh = errv.put_header();
+    errh.nlmsg_type = libc::NLMSG_ERROR as u16;
+    errh.nlmsg_flags = 0x300; // NLM_F_CAPPED | NLM_F_ACK_TLVS
+    let err = errv.put_extra_header::<libc::nlmsgerr>().unwrap();
+    err.error = -libc::ERANGE;
+    err.msg.nlmsg_seq = 2;
+    errv.put(2u16, &(offset as u32)).unwrap(); // NLMSGERR_ATTR_OFFS
+    let err = kernel_error(mnl::cb_run(errv.as_ref(), 0, 0, mnl::NOCB).unwrap_err());
+    assert!(err.seq() == Some(2));
+    let types: Vec<u16> = err
+        .attr_path(&nlv, 4)
+        .unwrap()
+        .iter()
+        .map(|a| a.atype())
+        .collect();
+    assert!(types == [2, 3, 5]);
 }
 
 // #[test]
@@ -763,7 +2206,7 @@ fn nlmsg_cb_run() {
 //                                    if attr.nla_type < data {
 //                                        return Ok(mnl::CbStatus::Ok);
 //                                    }
-//                                    Err(mnl::GenError::from(Error::new(ErrorKind::Other, "error")))
+//                                    Err(mnl::Error::from(Error::new(ErrorKind::Other, "error")))
 //                                }).is_ok());
 
 //     data = 3;
@@ -772,6 +2215,6 @@ fn nlmsg_cb_run() {
 //                                    if attr.nla_type < data {
 //                                        return Ok(mnl::CbStatus::Ok);
 //                                    }
-//                                    Err(mnl::GenError::from(Error::new(ErrorKind::Other, "error")))
+//                                    Err(mnl::Error::from(Error::new(ErrorKind::Other, "error")))
 //                                }).is_err());
 // }